@@ -0,0 +1,41 @@
+use chust::{
+    fen::Fen, game::Game, move_generation::MoveGenerator, perft::PerfTest,
+    search::best_moves_with_depth,
+};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+fn move_generation(c: &mut Criterion) {
+    let game = Fen::parse_game(KIWIPETE).unwrap();
+    c.bench_function("all_legal_moves kiwipete", |b| {
+        b.iter(|| {
+            let move_generator = MoveGenerator::new(black_box(&game));
+            move_generator.all_legal_moves(game.current_turn())
+        })
+    });
+}
+
+fn perft(c: &mut Criterion) {
+    let mut group = c.benchmark_group("perft");
+    group.sample_size(10);
+    group.bench_function("perft depth 3", |b| {
+        b.iter(|| PerfTest::new(Game::default(), black_box(3)).run_perft())
+    });
+    group.finish();
+}
+
+fn search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search");
+    group.sample_size(10);
+    group.bench_function("best_move depth 3", |b| {
+        b.iter(|| {
+            let mut game = Game::default();
+            best_moves_with_depth(&mut game, black_box(3))
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, move_generation, perft, search);
+criterion_main!(benches);
@@ -1,3 +1,6 @@
+use std::time::Duration;
+
+use chust::color::Color;
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -11,4 +14,75 @@ pub enum FullGameEvent {
 #[serde(rename_all = "camelCase")]
 pub struct GameState {
     pub moves: String,
+    // Remaining time and increment in milliseconds
+    #[serde(default)]
+    pub wtime: u64,
+    #[serde(default)]
+    pub btime: u64,
+    #[serde(default)]
+    pub winc: u64,
+    #[serde(default)]
+    pub binc: u64,
+    // Lichess status name, e.g. "started", "mate", "resign" or "draw"
+    #[serde(default = "default_status")]
+    pub status: String,
+    #[serde(default)]
+    pub winner: Option<String>,
+}
+
+fn default_status() -> String {
+    "started".to_owned()
+}
+
+impl GameState {
+    /// Returns the remaining time and increment of the color
+    pub fn clock(&self, color: Color) -> (Duration, Duration) {
+        let (time, inc) = match color {
+            Color::White => (self.wtime, self.winc),
+            Color::Black => (self.btime, self.binc),
+        };
+        (Duration::from_millis(time), Duration::from_millis(inc))
+    }
+
+    /// Returns true if the status says the game has ended
+    pub fn is_over(&self) -> bool {
+        !matches!(self.status.as_str(), "created" | "started")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn game_state_with_terminal_status() {
+        let json = r#"{
+            "type": "gameState",
+            "moves": "e2e4 e7e5 d1h5 b8c6 f1c4 g8f6 h5f7",
+            "wtime": 290000,
+            "btime": 285000,
+            "winc": 2000,
+            "binc": 2000,
+            "status": "mate",
+            "winner": "white"
+          }"#;
+        let event: FullGameEvent = serde_json::from_str(json).unwrap();
+        let FullGameEvent::GameState(state) = event else {
+            panic!("Wrong event type");
+        };
+        assert_eq!(state.status, "mate");
+        assert_eq!(state.winner.as_deref(), Some("white"));
+        assert!(state.is_over());
+    }
+
+    #[test]
+    fn game_state_without_status_is_running() {
+        let json = r#"{ "type": "gameState", "moves": "e2e4" }"#;
+        let event: FullGameEvent = serde_json::from_str(json).unwrap();
+        let FullGameEvent::GameState(state) = event else {
+            panic!("Wrong event type");
+        };
+        assert_eq!(state.winner, None);
+        assert!(!state.is_over());
+    }
 }
@@ -4,6 +4,7 @@ pub mod incoming_events;
 pub mod incoming_game_state;
 pub mod player;
 pub mod server;
+pub mod time_control;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -1,11 +1,14 @@
-use std::sync::mpsc;
+use std::{cell::Cell, sync::mpsc};
 
 use chust::{
     move_generation::MoveGenerator,
     moves::{Move, MoveType},
     players::PlayerInterface,
+    search::BotBasic,
 };
 
+use crate::time_control::MoveBudget;
+
 pub struct LichessPlayer {
     move_receiver: mpsc::Receiver<Move>,
 }
@@ -40,6 +43,41 @@ impl PlayerInterface for LichessPlayer {
     }
 }
 
+/// Plays with [BotBasic], searching only as deep and as long as the budget for the move allows.
+/// The budget is sent together with the opponent's move, before the first one the default bot plays.
+pub struct BudgetedBot {
+    budget_receiver: mpsc::Receiver<MoveBudget>,
+    budget: Cell<Option<MoveBudget>>,
+}
+
+impl BudgetedBot {
+    pub fn new(budget_receiver: mpsc::Receiver<MoveBudget>) -> Self {
+        Self {
+            budget_receiver,
+            budget: Cell::new(None),
+        }
+    }
+
+    // The bot for the newest budget received
+    fn bot(&self) -> BotBasic {
+        if let Some(budget) = self.budget_receiver.try_iter().last() {
+            self.budget.set(Some(budget));
+        }
+        match self.budget.get() {
+            Some(budget) => BotBasic::default()
+                .with_max_depth(budget.max_depth)
+                .with_time_limit(budget.time),
+            None => BotBasic::default(),
+        }
+    }
+}
+
+impl PlayerInterface for BudgetedBot {
+    fn make_move(&self, game: &chust::game::Game) -> Option<chust::moves::Move> {
+        self.bot().make_move(game)
+    }
+}
+
 pub struct LichessBot<P: PlayerInterface> {
     player: P,
 }
@@ -56,3 +94,54 @@ where
         res
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use chust::{fen::Fen, game::Game, moves::PromotionType, piece_type::PieceType};
+
+    use super::*;
+
+    #[test]
+    fn underpromotions_round_trip() {
+        let game = Fen::parse_game("2n1k3/3P4/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let (tx, rx) = mpsc::channel();
+        let player = LichessPlayer::new(rx);
+        for (uci, move_type) in [
+            (
+                "d7c8r",
+                MoveType::PromotionCapture(PromotionType::Rook, PieceType::Knight),
+            ),
+            ("d7d8n", MoveType::PromotionQuite(PromotionType::Knight)),
+        ] {
+            tx.send(uci.parse().unwrap()).unwrap();
+            let mov = player.make_move(&game).unwrap();
+            assert_eq!(mov.move_type, move_type);
+            assert_eq!(mov.to_string(), uci);
+        }
+    }
+
+    #[test]
+    fn low_time_budget_caps_depth() {
+        let (tx, rx) = mpsc::channel();
+        let bot = BudgetedBot::new(rx);
+        let game = Game::default();
+
+        tx.send(MoveBudget::new(
+            Duration::from_secs(2),
+            Duration::from_secs(1),
+        ))
+        .unwrap();
+        let (best_moves, stats) = bot.bot().search(&game);
+        assert_eq!(stats.depth, 1);
+        assert!(game.is_legal(&best_moves[0].unwrap().0));
+
+        // The budget is kept until a new one arrives
+        assert_eq!(bot.bot().search(&game).1.depth, 1);
+
+        tx.send(MoveBudget::new(Duration::from_secs(600), Duration::ZERO))
+            .unwrap();
+        assert!(bot.bot().search(&game).1.depth > 1);
+    }
+}
@@ -1,12 +1,12 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, time::Duration};
 
 use anyhow::Context;
 use chust::{
     color::Color,
+    game::Game,
     moves::Move,
+    piece_type::PieceType,
     play_game::{PlayGame, TurnResult},
-    players::player_cli::CliPlayer,
-    search,
 };
 use dotenv::dotenv;
 use reqwest::Client;
@@ -15,20 +15,81 @@ use tokio::task::spawn_blocking;
 use crate::{
     incoming_events::Event,
     incoming_game_state::{FullGameEvent, GameState},
-    player::LichessPlayer,
+    player::{BudgetedBot, LichessPlayer},
+    time_control::MoveBudget,
 };
 
 const BASE_URL: &str = "https://lichess.org/api";
+const RECONNECT_BASE_DELAY: Duration = Duration::from_secs(1);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(60);
+const SEND_RETRY_BASE_DELAY: Duration = Duration::from_millis(500);
 
 pub struct LichessServer {
     running_games: HashMap<String, RunningGame>,
     client: Client,
     auth: String,
+    send_policy: SendPolicy,
+}
+
+/// How often and how long bot actions are tried to be sent to lichess.
+/// Set with `LICHESS_SEND_ATTEMPTS` and `LICHESS_SEND_TIMEOUT_SECS`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SendPolicy {
+    attempts: u32,
+    timeout: Duration,
+}
+
+impl Default for SendPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            timeout: Duration::from_secs(10),
+        }
+    }
+}
+
+impl SendPolicy {
+    fn from_env() -> Self {
+        let default = Self::default();
+        let var = |name| {
+            std::env::var(name)
+                .ok()
+                .and_then(|value| value.parse::<u64>().ok())
+        };
+        Self {
+            attempts: var("LICHESS_SEND_ATTEMPTS")
+                .map_or(default.attempts, |attempts| (attempts as u32).max(1)),
+            timeout: var("LICHESS_SEND_TIMEOUT_SECS").map_or(default.timeout, Duration::from_secs),
+        }
+    }
+
+    // The delay before the next try after the given failed attempt (starting at 0), None once all attempts are used
+    fn retry_delay(&self, failed_attempt: u32) -> Option<Duration> {
+        (failed_attempt + 1 < self.attempts)
+            .then(|| SEND_RETRY_BASE_DELAY.saturating_mul(2u32.saturating_pow(failed_attempt)))
+    }
+}
+
+// What the play thread wants to send to lichess
+enum BotAction {
+    Move(Move),
+    ClaimDraw,
+}
+
+impl BotAction {
+    // Moves are sent in uci, promotions keep their piece letter (e.g. e7e8n)
+    fn url(&self, game_id: &str) -> String {
+        match self {
+            BotAction::Move(mov) => format!("{BASE_URL}/bot/game/{game_id}/move/{mov}"),
+            BotAction::ClaimDraw => format!("{BASE_URL}/bot/game/{game_id}/draw/yes"),
+        }
+    }
 }
 
 struct RunningGame {
     bot_color: Color,
     tx: std::sync::mpsc::Sender<Move>,
+    budget_tx: std::sync::mpsc::Sender<MoveBudget>,
     listen_handle: tokio::task::JoinHandle<()>,
     playing_handle: tokio::task::JoinHandle<()>,
 }
@@ -41,7 +102,7 @@ impl RunningGame {
             .split_whitespace()
             .map(|m| {
                 m.parse::<Move>()
-                    .expect(&format!("Failed decoding move {m}"))
+                    .unwrap_or_else(|_| panic!("Failed decoding move {m}"))
             })
             .collect::<Vec<_>>();
 
@@ -61,6 +122,16 @@ impl RunningGame {
 
         // First we update all the moves
         if move_color != self.bot_color {
+            let (remaining, increment) = state.clock(self.bot_color);
+            let budget = MoveBudget::new(remaining, increment);
+            println!(
+                "Budget for next move: {:?} (max depth {})",
+                budget.time, budget.max_depth
+            );
+            // Sent before the move, so the bot has it when it is its turn
+            self.budget_tx
+                .send(budget)
+                .expect("Failed to send move budget");
             self.tx
                 .send(server_moves.into_iter().last().expect("No move given"))
                 .expect("Failed to send state");
@@ -78,6 +149,7 @@ impl LichessServer {
             running_games: HashMap::new(),
             client: Client::default(),
             auth,
+            send_policy: SendPolicy::from_env(),
         })
     }
 
@@ -85,14 +157,22 @@ impl LichessServer {
         let (event_tx, mut event_rx) = tokio::sync::mpsc::channel(24);
         let (game_tx, mut game_rx) = tokio::sync::mpsc::channel(100);
 
-        tokio::spawn(handle_events(
+        let events_handle = tokio::spawn(handle_events(
             self.client.clone(),
             self.auth.clone(),
             event_tx,
         ));
 
+        let shutdown = tokio::signal::ctrl_c();
+        tokio::pin!(shutdown);
+
         loop {
             tokio::select! {
+                res = &mut shutdown => {
+                    res.context("Failed to listen for ctrl-c")?;
+                    println!("Shutting down");
+                    break;
+                }
                 Some(event) = event_rx.recv() => {
                     if let Err(e) = self.event_received(event, game_tx.clone()).await {
                         println!("Error processing event: {:?}", e);
@@ -101,12 +181,38 @@ impl LichessServer {
                 Some(game_event) = game_rx.recv() => {
                     let (game_id, state) = game_event;
 
-                    if let Some(game) = self.running_games.get_mut(&game_id) {
+                    if state.is_over() {
+                        println!(
+                            "Game {game_id} ended with status '{}' (winner: {:?})",
+                            state.status, state.winner
+                        );
+                        self.stop_game(&game_id);
+                    } else if let Some(game) = self.running_games.get_mut(&game_id) {
                         game.receive_lichess_move(state);
                     }
                 }
             }
         }
+
+        events_handle.abort();
+        self.stop_all_games();
+        Ok(())
+    }
+
+    fn stop_game(&mut self, game_id: &str) {
+        if let Some(game) = self.running_games.remove(game_id) {
+            game.listen_handle.abort();
+            game.playing_handle.abort();
+            println!("Stopped game {game_id}");
+        }
+    }
+
+    fn stop_all_games(&mut self) {
+        for (game_id, game) in self.running_games.drain() {
+            game.listen_handle.abort();
+            game.playing_handle.abort();
+            println!("Stopped game {game_id}");
+        }
     }
 
     async fn event_received(
@@ -116,6 +222,13 @@ impl LichessServer {
     ) -> anyhow::Result<()> {
         match event {
             Event::Challenge { challenge } => {
+                if let Some(reason) = challenge_decline_reason(&challenge.variant.key) {
+                    println!(
+                        "Declining challenge: {} from '{}' ({reason})",
+                        challenge.id, challenge.challenger.name
+                    );
+                    return self.send_challenge_declined(challenge.id, reason).await;
+                }
                 println!(
                     "Accepting challenge: {} from '{}'",
                     challenge.id, challenge.challenger.name
@@ -138,6 +251,7 @@ impl LichessServer {
                     game_start,
                     self.client.clone(),
                     self.auth.clone(),
+                    self.send_policy,
                     game_sender,
                 )
                 .await?;
@@ -149,11 +263,8 @@ impl LichessServer {
                     "Game finished against: {:?}",
                     game_end.game.opponent.username
                 );
-                if let Some(removed_game) = self.running_games.remove(&game_end.game.game_id) {
-                    removed_game.listen_handle.abort();
-                    removed_game.playing_handle.abort();
-                    println!("Removed game from running handles");
-                }
+                // The game may already be stopped by a terminal game state
+                self.stop_game(&game_end.game.game_id);
                 Ok(())
             }
         }
@@ -175,20 +286,122 @@ impl LichessServer {
 
         Ok(())
     }
+
+    async fn send_challenge_declined(
+        &self,
+        challenge_id: String,
+        reason: &str,
+    ) -> anyhow::Result<()> {
+        let res = self
+            .client
+            .post(format!("{BASE_URL}/challenge/{challenge_id}/decline"))
+            .header("Authorization", self.auth.clone())
+            .form(&[("reason", reason)])
+            .send()
+            .await
+            .context("Failed to send challenge decline request")?;
+
+        println!(
+            "{}",
+            res.text().await.context("Failed to get response body")?
+        );
+
+        Ok(())
+    }
+}
+
+// Returns the lichess decline reason, if we can't play the challenge.
+// Only standard chess is supported, castling is hardcoded for the standard start position.
+fn challenge_decline_reason(variant_key: &str) -> Option<&'static str> {
+    (variant_key != "standard").then_some("standard")
+}
+
+// Without pawns, rooks or queens a single minor piece can't force mate
+fn has_mating_material(game: &Game, color: Color) -> bool {
+    let material = game.material(color);
+    let heavy = material[PieceType::Pawn as usize]
+        + material[PieceType::Rook as usize]
+        + material[PieceType::Queen as usize];
+    let minor = material[PieceType::Knight as usize] + material[PieceType::Bishop as usize];
+    heavy > 0 || minor > 1
+}
+
+// Doubles the delay with every failed attempt to reconnect, up to a maximum
+fn reconnect_backoff(attempt: u32) -> Duration {
+    RECONNECT_BASE_DELAY
+        .checked_mul(2u32.saturating_pow(attempt))
+        .map_or(RECONNECT_MAX_DELAY, |delay| delay.min(RECONNECT_MAX_DELAY))
+}
+
+// Posts the url, retrying with a growing delay on network and server errors
+async fn send_action(
+    client: &Client,
+    auth: &str,
+    url: &str,
+    policy: SendPolicy,
+) -> anyhow::Result<()> {
+    let mut failed_attempt = 0;
+    loop {
+        let result = client
+            .post(url)
+            .header("Authorization", auth)
+            .timeout(policy.timeout)
+            .send()
+            .await;
+        let error = match result {
+            Ok(res) if res.status().is_success() => return Ok(()),
+            // Retrying won't make lichess accept it
+            Ok(res) if res.status().is_client_error() => {
+                anyhow::bail!("Lichess rejected {url} with {}", res.status())
+            }
+            Ok(res) => anyhow::anyhow!("Lichess answered {url} with {}", res.status()),
+            Err(e) => anyhow::Error::new(e).context(format!("Failed to send {url}")),
+        };
+        let Some(delay) = policy.retry_delay(failed_attempt) else {
+            return Err(error);
+        };
+        println!("Retrying in {delay:?}: {error:?}");
+        tokio::time::sleep(delay).await;
+        failed_attempt += 1;
+    }
 }
 
 // This will wait for any event that is received
-async fn handle_events(
-    client: Client,
-    auth: String,
-    sender: tokio::sync::mpsc::Sender<Event>,
+// Reconnects to the event stream when it ends or fails, until nobody listens to the events anymore
+async fn handle_events(client: Client, auth: String, sender: tokio::sync::mpsc::Sender<Event>) {
+    let mut attempt = 0;
+    loop {
+        match stream_events(&client, &auth, &sender, &mut attempt).await {
+            Ok(()) => println!("Event stream closed"),
+            Err(e) => println!("Error handling events: {:?}", e),
+        }
+        if sender.is_closed() {
+            break;
+        }
+
+        let delay = reconnect_backoff(attempt);
+        println!("Reconnecting to event stream in {delay:?}");
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+async fn stream_events(
+    client: &Client,
+    auth: &str,
+    sender: &tokio::sync::mpsc::Sender<Event>,
+    attempt: &mut u32,
 ) -> anyhow::Result<()> {
     let mut res = client
         .get(format!("{BASE_URL}/stream/event"))
-        .header("Authorization", auth.clone())
+        .header("Authorization", auth)
         .send()
         .await
-        .expect("Failed to send request");
+        .context("Failed to send request")?
+        .error_for_status()
+        .context("Event stream request failed")?;
+    // We are connected again
+    *attempt = 0;
     while let Some(chunk) = res.chunk().await.context("Failed to get chunk")? {
         let chunk = std::str::from_utf8(&chunk).context("Failed to parse chunk as utf8")?;
         if chunk.trim().is_empty() {
@@ -214,6 +427,7 @@ async fn start_game(
     game_start: crate::incoming_events::game::GameStart,
     client: Client,
     auth: String,
+    send_policy: SendPolicy,
     send_state: tokio::sync::mpsc::Sender<(String, GameState)>,
 ) -> anyhow::Result<RunningGame> {
     let game = chust::fen::Fen::parse_game(&game_start.game.fen)?;
@@ -224,18 +438,17 @@ async fn start_game(
         "black" => Color::Black,
         _ => anyhow::bail!("Invalid color"),
     };
-    let (move_tx, mut move_rx) = tokio::sync::mpsc::channel(100);
+    let (action_tx, mut action_rx) = tokio::sync::mpsc::channel::<BotAction>(100);
 
     let game_event_handle = tokio::spawn(async move {
         let client = client.clone();
         loop {
             tokio::select! {
-                Some(mov) = move_rx.recv() => {
-                    client.post(format!("{BASE_URL}/bot/game/{}/move/{}", game_id, mov))
-                        .header("Authorization", auth.clone())
-                        .send()
-                        .await
-                        .expect("Failed to send move");
+                Some(action) = action_rx.recv() => {
+                    // Don't let a network error end the task and abandon the game
+                    if let Err(e) = send_action(&client, &auth, &action.url(&game_id), send_policy).await {
+                        println!("Giving up on sending bot action: {:?}", e);
+                    }
                 }
                 Err(e) = handle_game_events(client.clone(), auth.clone(), game_id.clone(), send_state.clone()) => {
                     println!("Error handling game events: {:?}", e);
@@ -248,10 +461,11 @@ async fn start_game(
     });
 
     let (tx, rx) = std::sync::mpsc::channel();
+    let (budget_tx, budget_rx) = std::sync::mpsc::channel();
 
     let play_thread = spawn_blocking(move || {
         let mut game = PlayGame::default()
-            .connect_player(Box::new(search::BotBasic), bot_player_color)
+            .connect_player(Box::new(BudgetedBot::new(budget_rx)), bot_player_color)
             .expect_waiting()
             .connect_player(
                 Box::new(LichessPlayer::new(rx)),
@@ -260,11 +474,37 @@ async fn start_game(
             .expect_ready()
             .start(game);
 
-        while let TurnResult::InProgress(mov, color) = game.wait_for_move() {
+        loop {
+            let (mov, color) = match game.wait_for_move() {
+                TurnResult::InProgress(mov, color) => (mov, color),
+                TurnResult::Checkmate(winner) if winner == bot_player_color => {
+                    println!("Won by checkmate");
+                    break;
+                }
+                TurnResult::Checkmate(_) => {
+                    println!("Lost by checkmate");
+                    break;
+                }
+                TurnResult::Stalemate => {
+                    println!("Draw by stalemate");
+                    break;
+                }
+                TurnResult::PlayerNotMakingMoves => break,
+            };
             println!("Made move {mov}");
             game.game().print_pieces();
             if color == bot_player_color {
-                move_tx.blocking_send(mov).expect("Failed to send move");
+                action_tx
+                    .blocking_send(BotAction::Move(mov))
+                    .expect("Failed to send move");
+            } else if game.game().can_claim_draw().is_some()
+                && (!has_mating_material(game.game(), bot_player_color)
+                    || game.game().is_likely_draw_material())
+            {
+                println!("Claiming draw");
+                action_tx
+                    .blocking_send(BotAction::ClaimDraw)
+                    .expect("Failed to send draw claim");
             }
         }
     });
@@ -272,6 +512,7 @@ async fn start_game(
     let running_game = RunningGame {
         bot_color: bot_player_color,
         tx,
+        budget_tx,
         listen_handle: game_event_handle,
         playing_handle: play_thread,
     };
@@ -309,3 +550,78 @@ async fn handle_game_events(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use chust::{
+        moves::{MoveType, PromotionType},
+        position::Position,
+    };
+
+    use super::*;
+
+    #[test]
+    fn underpromotion_url() {
+        let mov = Move::new(
+            Position::D7,
+            Position::C8,
+            MoveType::PromotionCapture(PromotionType::Rook, PieceType::Knight),
+        );
+        assert_eq!(mov.to_string(), "d7c8r");
+        assert_eq!(
+            BotAction::Move(mov).url("abc"),
+            format!("{BASE_URL}/bot/game/abc/move/d7c8r")
+        );
+        assert_eq!(
+            BotAction::ClaimDraw.url("abc"),
+            format!("{BASE_URL}/bot/game/abc/draw/yes")
+        );
+    }
+
+    #[test]
+    fn decline_non_standard_variants() {
+        assert_eq!(challenge_decline_reason("standard"), None);
+        assert_eq!(challenge_decline_reason("chess960"), Some("standard"));
+        assert_eq!(challenge_decline_reason("fromPosition"), Some("standard"));
+        assert_eq!(challenge_decline_reason("atomic"), Some("standard"));
+    }
+
+    #[test]
+    fn mating_material() {
+        let game: Game = "4k3/8/8/8/8/8/8/2B1K3 w - - 0 1".parse().unwrap();
+        assert!(!has_mating_material(&game, Color::White));
+        assert!(!has_mating_material(&game, Color::Black));
+        let game: Game = "4k3/8/8/8/8/8/8/1NB1K3 w - - 0 1".parse().unwrap();
+        assert!(has_mating_material(&game, Color::White));
+        let game: Game = "4k3/7p/8/8/8/8/8/4K3 w - - 0 1".parse().unwrap();
+        assert!(has_mating_material(&game, Color::Black));
+    }
+
+    #[test]
+    fn reconnect_backoff_doubles_up_to_max() {
+        assert_eq!(reconnect_backoff(0), Duration::from_secs(1));
+        assert_eq!(reconnect_backoff(1), Duration::from_secs(2));
+        assert_eq!(reconnect_backoff(5), Duration::from_secs(32));
+        assert_eq!(reconnect_backoff(6), RECONNECT_MAX_DELAY);
+        assert_eq!(reconnect_backoff(100), RECONNECT_MAX_DELAY);
+    }
+
+    #[test]
+    fn send_retries_with_backoff() {
+        let policy = SendPolicy::default();
+        assert_eq!(policy.retry_delay(0), Some(Duration::from_millis(500)));
+        assert_eq!(policy.retry_delay(1), Some(Duration::from_secs(1)));
+        assert_eq!(policy.retry_delay(2), None);
+
+        let policy = SendPolicy {
+            attempts: 1,
+            ..policy
+        };
+        assert_eq!(policy.retry_delay(0), None);
+        let policy = SendPolicy {
+            attempts: u32::MAX,
+            ..policy
+        };
+        assert!(policy.retry_delay(100).is_some());
+    }
+}
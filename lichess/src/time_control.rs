@@ -0,0 +1,59 @@
+use std::time::Duration;
+
+/// Below this remaining time we only play fast moves to not lose on time
+const LOW_TIME: Duration = Duration::from_secs(5);
+/// Depth searched when there is enough time
+const DEFAULT_DEPTH: u32 = 4;
+/// Expected number of moves left in the game, the remaining time is split over them
+const MOVES_TO_GO: u32 = 40;
+
+/// The time and depth the bot can spend on the next move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MoveBudget {
+    pub time: Duration,
+    pub max_depth: u32,
+}
+
+impl MoveBudget {
+    pub fn new(remaining: Duration, increment: Duration) -> Self {
+        if remaining < LOW_TIME {
+            // Only use a part of the increment, so the clock never goes down.
+            // Never more than a tenth of the remaining time, in case the increment is large.
+            return Self {
+                time: (increment / 4).min(remaining / 10),
+                max_depth: 1,
+            };
+        }
+
+        let time = remaining / MOVES_TO_GO + increment * 3 / 4;
+        Self {
+            time: time.min(remaining / 2),
+            max_depth: DEFAULT_DEPTH,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn budget_with_enough_time() {
+        let budget = MoveBudget::new(Duration::from_secs(60), Duration::from_secs(2));
+        assert_eq!(budget.time, Duration::from_millis(3000));
+        assert_eq!(budget.max_depth, DEFAULT_DEPTH);
+    }
+
+    #[test]
+    fn budget_on_low_time() {
+        let budget = MoveBudget::new(Duration::from_secs(2), Duration::from_secs(1));
+        assert_eq!(budget.time, Duration::from_millis(200));
+        assert!(budget.time < Duration::from_secs(1));
+        assert_eq!(budget.max_depth, 1);
+
+        // No increment, we still have to move fast
+        let budget = MoveBudget::new(Duration::from_secs(2), Duration::ZERO);
+        assert_eq!(budget.time, Duration::ZERO);
+        assert_eq!(budget.max_depth, 1);
+    }
+}
@@ -9,6 +9,7 @@ use chust::{
     play_game::{self, PlayGame},
     players::player_cli::CliPlayer,
     position::Position,
+    print_board::{DefaultBoardPrinter, LastMovePrinter},
     search::{best_moves, BotBasic},
 };
 
@@ -44,11 +45,10 @@ fn main() -> anyhow::Result<()> {
                         .parse::<Position>()
                         .context("Please provide a position to show possible moves")?;
 
-                    let generator = MoveGenerator::new(&game);
-                    let legal_moves = generator.legal_moves(&position);
+                    let destinations = game.legal_destinations(&position);
 
                     game.print_custom(|p: Position, _: &Game| {
-                        if legal_moves.iter().any(|m| m.to == p) {
+                        if destinations.contains(&p) {
                             'X'
                         } else {
                             ' '
@@ -90,7 +90,7 @@ fn main() -> anyhow::Result<()> {
                         })
                         .with_context(|| format!("Could not find valid move {:?}", legal_moves))?;
                     let _ = game.make_move(*mov);
-                    game.print_pieces();
+                    game.print_custom(LastMovePrinter::new(DefaultBoardPrinter));
                 }
                 "start" => {
                     let playing_game = PlayGame::default();
@@ -98,7 +98,7 @@ fn main() -> anyhow::Result<()> {
                         .connect_player(Box::new(CliPlayer), Color::White)
                         .expect_waiting();
                     let playing_game = playing_game
-                        .connect_player(Box::new(BotBasic), Color::Black)
+                        .connect_player(Box::new(BotBasic::default()), Color::Black)
                         .expect_ready();
 
                     let to_play = Fen::parse_game(&Fen::from_game(&game)).unwrap();
@@ -107,8 +107,8 @@ fn main() -> anyhow::Result<()> {
 
                     loop {
                         match playing_game.wait_for_move() {
-                            play_game::TurnResult::Checkmate => {
-                                println!("Checkmate!");
+                            play_game::TurnResult::Checkmate(winner) => {
+                                println!("Checkmate! {winner:?} wins");
                                 break;
                             }
                             play_game::TurnResult::Stalemate => {
@@ -116,7 +116,9 @@ fn main() -> anyhow::Result<()> {
                                 break;
                             }
                             play_game::TurnResult::InProgress(_, _) => {
-                                playing_game.game().print_pieces();
+                                playing_game
+                                    .game()
+                                    .print_custom(LastMovePrinter::new(DefaultBoardPrinter));
                             }
                             play_game::TurnResult::PlayerNotMakingMoves => {
                                 println!(
@@ -133,6 +135,8 @@ fn main() -> anyhow::Result<()> {
                     for (mov, score) in search.into_iter().flatten() {
                         println!("{}: {}", mov, score);
                     }
+                    #[cfg(feature = "timers")]
+                    chust::scoped_timer::TimerRegistry::print_summary();
                 }
                 "um" => {
                     game.unmake_move();
@@ -140,6 +144,7 @@ fn main() -> anyhow::Result<()> {
                 }
                 "print" => {
                     game.print_pieces();
+                    println!("{}", game.status_line());
                 }
                 "bitboard" => {
                     let which = BitBoardPrinter::ALL_IDENTIFIED
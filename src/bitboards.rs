@@ -1,11 +1,13 @@
-use std::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Not};
+use std::{
+    cell::OnceCell,
+    ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, BitXor, BitXorAssign, Not, Sub},
+};
 
 use crate::{
-    color::Color, game::Game, move_generation::MoveGenerator, piece_type::PieceType,
-    position::Position, print_board::BoardPrinter,
+    color::Color, game::Game, piece_type::PieceType, position::Position, print_board::BoardPrinter,
 };
 
-#[derive(Default, Clone, Debug, PartialEq)]
+#[derive(Default, Clone, Debug)]
 pub struct GameBitBoards {
     pub white_pawns: Bitboard,
     pub white_knights: Bitboard,
@@ -23,8 +25,7 @@ pub struct GameBitBoards {
     pub white_king: Position,
     pub black_king: Position,
 
-    pub white_attacks: Bitboard,
-    pub black_attacks: Bitboard,
+    attacks: LazyAttacks,
 
     pub white_pinned: Vec<Bitboard>,
     pub black_pinned: Vec<Bitboard>,
@@ -38,19 +39,6 @@ impl GameBitBoards {
         let mut this = Self::default();
         this.generate_pieces(game);
 
-        // Possible attacks
-        let move_generator = MoveGenerator::new(game);
-        for position in this.white_pieces.iter() {
-            for attack in move_generator.possible_attacking_moves(&position) {
-                this.white_attacks |= 1 << attack.to.board_index();
-            }
-        }
-        for position in this.black_pieces.iter() {
-            for attack in move_generator.possible_attacking_moves(&position) {
-                this.black_attacks |= 1 << attack.to.board_index();
-            }
-        }
-
         // Pins
         this.generate_king_pins_and_checks(game, Color::White);
         this.generate_king_pins_and_checks(game, Color::Black);
@@ -59,30 +47,28 @@ impl GameBitBoards {
     }
 
     fn generate_pieces(&mut self, game: &Game) {
-        for (position, piece) in game.board().iter() {
+        for (position, piece) in game.board().iter_pieces() {
             let bitboard = 1 << position.board_index();
-            if let Some(piece) = piece {
-                match (piece.piece_type(), piece.color()) {
-                    (PieceType::Pawn, Color::White) => self.white_pawns |= bitboard,
-                    (PieceType::Knight, Color::White) => self.white_knights |= bitboard,
-                    (PieceType::Bishop, Color::White) => self.white_bishops |= bitboard,
-                    (PieceType::Rook, Color::White) => self.white_rooks |= bitboard,
-                    (PieceType::Queen, Color::White) => self.white_queens |= bitboard,
-                    (PieceType::Pawn, Color::Black) => self.black_pawns |= bitboard,
-                    (PieceType::Knight, Color::Black) => self.black_knights |= bitboard,
-                    (PieceType::Bishop, Color::Black) => self.black_bishops |= bitboard,
-                    (PieceType::Rook, Color::Black) => self.black_rooks |= bitboard,
-                    (PieceType::Queen, Color::Black) => self.black_queens |= bitboard,
-                    (PieceType::King, Color::White) => self.white_king = position,
-                    (PieceType::King, Color::Black) => self.black_king = position,
+            match (piece.piece_type(), piece.color()) {
+                (PieceType::Pawn, Color::White) => self.white_pawns |= bitboard,
+                (PieceType::Knight, Color::White) => self.white_knights |= bitboard,
+                (PieceType::Bishop, Color::White) => self.white_bishops |= bitboard,
+                (PieceType::Rook, Color::White) => self.white_rooks |= bitboard,
+                (PieceType::Queen, Color::White) => self.white_queens |= bitboard,
+                (PieceType::Pawn, Color::Black) => self.black_pawns |= bitboard,
+                (PieceType::Knight, Color::Black) => self.black_knights |= bitboard,
+                (PieceType::Bishop, Color::Black) => self.black_bishops |= bitboard,
+                (PieceType::Rook, Color::Black) => self.black_rooks |= bitboard,
+                (PieceType::Queen, Color::Black) => self.black_queens |= bitboard,
+                (PieceType::King, Color::White) => self.white_king = position,
+                (PieceType::King, Color::Black) => self.black_king = position,
+            }
+            match piece.color() {
+                Color::White => {
+                    self.white_pieces |= bitboard;
                 }
-                match piece.color() {
-                    Color::White => {
-                        self.white_pieces |= bitboard;
-                    }
-                    Color::Black => {
-                        self.black_pieces |= bitboard;
-                    }
+                Color::Black => {
+                    self.black_pieces |= bitboard;
                 }
             }
         }
@@ -98,21 +84,12 @@ impl GameBitBoards {
         let mut pins = Vec::default();
         let mut checks = Vec::default();
 
-        for direction in &[
-            (1, 0, &[PieceType::Rook, PieceType::Queen]),
-            (1, 1, &[PieceType::Bishop, PieceType::Queen]),
-            (0, 1, &[PieceType::Rook, PieceType::Queen]),
-            (-1, 1, &[PieceType::Bishop, PieceType::Queen]),
-            (-1, 0, &[PieceType::Rook, PieceType::Queen]),
-            (-1, -1, &[PieceType::Bishop, PieceType::Queen]),
-            (0, -1, &[PieceType::Rook, PieceType::Queen]),
-            (1, -1, &[PieceType::Bishop, PieceType::Queen]),
-        ] {
+        for (dx, dy) in KING_DIRECTIONS {
             let mut position = king_position;
             let mut count = 0;
             let mut pinned = Bitboard::default();
             loop {
-                position = match position.offset(direction.0, direction.1) {
+                position = match position.offset(dx, dy) {
                     Some(position) => position,
                     None => break,
                 };
@@ -120,7 +97,7 @@ impl GameBitBoards {
 
                 if let Some(piece) = game.board().piece_at(&position) {
                     if piece.color() != color {
-                        if direction.2.contains(&piece.piece_type()) {
+                        if piece.piece_type().slides_in_direction(dx, dy) {
                             if count == 0 {
                                 checks.push(pinned);
                             } else if count == 1 {
@@ -142,17 +119,13 @@ impl GameBitBoards {
         }
 
         // Find pawn and knight checks as well.
-        let move_generator = MoveGenerator::new(game);
-        for piece_position in self
-            .knights(color.opposite())
-            .iter()
-            .chain(self.pawns(color.opposite()).iter())
-        {
-            for attack in move_generator.possible_attacking_moves(&piece_position) {
-                if attack.to == king_position {
-                    checks.push(Bitboard::from(1 << attack.from.board_index()));
-                }
-            }
+        // A piece on the king field attacks the same fields, that attack the king
+        let knight_checks =
+            Bitboard::knight_attacks(&king_position) & self.knights(color.opposite());
+        let pawn_checks =
+            Bitboard::pawn_attacks(color, &king_position) & self.pawns(color.opposite());
+        for checker in knight_checks.iter().chain(pawn_checks.iter()) {
+            checks.push(Bitboard::from(checker));
         }
 
         match color {
@@ -217,11 +190,47 @@ impl GameBitBoards {
         }
     }
 
+    /// Returns all fields attacked by the color, including fields of its own pieces.
+    /// Computed on the first call, positions that never ask for attacks don't pay for them.
     pub fn attacks(&self, color: Color) -> Bitboard {
-        match color {
-            Color::White => self.white_attacks,
-            Color::Black => self.black_attacks,
+        let cell = match color {
+            Color::White => &self.attacks.white,
+            Color::Black => &self.attacks.black,
+        };
+        *cell.get_or_init(|| self.generate_attacks(color))
+    }
+
+    fn generate_attacks(&self, color: Color) -> Bitboard {
+        let mut attacks = Bitboard::default();
+        for position in self.pawns(color).iter() {
+            attacks |= Bitboard::pawn_attacks(color, &position);
+        }
+        for position in self.knights(color).iter() {
+            attacks |= Bitboard::knight_attacks(&position);
+        }
+        attacks |= Bitboard::king_attacks(&self.king(color));
+
+        // Sliders attack through the enemy king, so it can't step back on the ray
+        let blockers =
+            (self.white_pieces | self.black_pieces) & !Bitboard::from(self.king(color.opposite()));
+        for piece_type in [PieceType::Bishop, PieceType::Rook, PieceType::Queen] {
+            for position in self.of_type(piece_type, color).iter() {
+                for (dx, dy) in KING_DIRECTIONS {
+                    if !piece_type.slides_in_direction(dx, dy) {
+                        continue;
+                    }
+                    let mut current = position;
+                    while let Some(next) = current.offset(dx, dy) {
+                        attacks |= Bitboard::from(next);
+                        if blockers.contains(&next) {
+                            break;
+                        }
+                        current = next;
+                    }
+                }
+            }
         }
+        attacks
     }
 
     pub fn pinned(&self, color: Color) -> &[Bitboard] {
@@ -231,6 +240,14 @@ impl GameBitBoards {
         }
     }
 
+    /// Returns the squares of the pieces pinned to the king of the given color.
+    pub fn pinned_pieces(&self, color: Color) -> Bitboard {
+        self.pinned(color)
+            .iter()
+            .fold(Bitboard::default(), |pinned, ray| pinned | *ray)
+            & self.pieces(color)
+    }
+
     pub fn blockable_checks(&self, color: Color) -> &[Bitboard] {
         match color {
             Color::White => &self.white_blockable_check,
@@ -238,15 +255,35 @@ impl GameBitBoards {
         }
     }
 
+    /// Returns the squares of all pieces giving check to the king of the given color.
+    pub fn checkers(&self, color: Color) -> Bitboard {
+        // Every check ray only contains empty squares and the checking piece itself
+        self.blockable_checks(color)
+            .iter()
+            .fold(Bitboard::default(), |checkers, ray| checkers | *ray)
+            & self.pieces(color.opposite())
+    }
+
     // Helper functions for evaluation
+    /// Returns the bitboard of the pieces with the given type and color
+    pub fn of_type(&self, piece_type: PieceType, color: Color) -> Bitboard {
+        match piece_type {
+            PieceType::Pawn => self.pawns(color),
+            PieceType::Knight => self.knights(color),
+            PieceType::Bishop => self.bishops(color),
+            PieceType::Rook => self.rooks(color),
+            PieceType::Queen => self.queens(color),
+            PieceType::King => Bitboard::from(self.king(color)),
+        }
+    }
+
     pub fn material(&self, color: Color) -> i32 {
-        let mut material = 0;
-        material += self.pawn_material(color);
-        material += self.knight_material(color);
-        material += self.bishop_material(color);
-        material += self.rook_material(color);
-        material += self.queen_material(color);
-        material
+        // The king is always on the board and not counted as material
+        PieceType::ALL
+            .iter()
+            .filter(|piece_type| **piece_type != PieceType::King)
+            .map(|piece_type| self.of_type(*piece_type, color).count() as i32 * piece_type.value())
+            .sum()
     }
 
     pub fn pawn_material(&self, color: Color) -> i32 {
@@ -268,8 +305,174 @@ impl GameBitBoards {
     pub fn queen_material(&self, color: Color) -> i32 {
         self.queens(color).iter().count() as i32 * PieceType::Queen.value()
     }
+
+    /// Bonus for having both bishops and a penalty for a lone knight in open positions.
+    pub fn minor_piece_score(&self, color: Color) -> i32 {
+        let mut score = 0;
+
+        // Only count bishops on different colored squares as pair
+        let bishops = self.bishops(color);
+        if bishops & LIGHT_SQUARES != 0 && bishops & !LIGHT_SQUARES != 0 {
+            score += BISHOP_PAIR_BONUS;
+        }
+
+        let total_pawns =
+            self.pawns(Color::White).iter().count() + self.pawns(Color::Black).iter().count();
+        if total_pawns <= OPEN_POSITION_MAX_PAWNS && self.knights(color).iter().count() == 1 {
+            score -= LONE_KNIGHT_PENALTY;
+        }
+
+        score
+    }
+
+    /// Bonus for rooks on files without friendly pawns (semi open) or without any pawns (open).
+    pub fn rook_file_score(&self, color: Color) -> i32 {
+        let friendly_pawns = self.pawns(color);
+        let all_pawns = friendly_pawns | self.pawns(color.opposite());
+
+        self.rooks(color)
+            .iter()
+            .map(|rook| {
+                let file = Bitboard::file(rook.file());
+                if (all_pawns & file).inner() == 0 {
+                    ROOK_OPEN_FILE_BONUS
+                } else if (friendly_pawns & file).inner() == 0 {
+                    ROOK_SEMI_OPEN_FILE_BONUS
+                } else {
+                    0
+                }
+            })
+            .sum()
+    }
 }
 
+/// Compares all bitboards except the cached attacks.
+/// Those are computed from the pieces, so they are equal whenever the pieces are.
+impl PartialEq for GameBitBoards {
+    fn eq(&self, other: &Self) -> bool {
+        let Self {
+            white_pawns,
+            white_knights,
+            white_bishops,
+            white_rooks,
+            white_queens,
+            black_pawns,
+            black_knights,
+            black_bishops,
+            black_rooks,
+            black_queens,
+            black_pieces,
+            white_pieces,
+            white_king,
+            black_king,
+            attacks: _,
+            white_pinned,
+            black_pinned,
+            white_blockable_check,
+            black_blockable_check,
+        } = self;
+        *white_pawns == other.white_pawns
+            && *white_knights == other.white_knights
+            && *white_bishops == other.white_bishops
+            && *white_rooks == other.white_rooks
+            && *white_queens == other.white_queens
+            && *black_pawns == other.black_pawns
+            && *black_knights == other.black_knights
+            && *black_bishops == other.black_bishops
+            && *black_rooks == other.black_rooks
+            && *black_queens == other.black_queens
+            && *black_pieces == other.black_pieces
+            && *white_pieces == other.white_pieces
+            && *white_king == other.white_king
+            && *black_king == other.black_king
+            && *white_pinned == other.white_pinned
+            && *black_pinned == other.black_pinned
+            && *white_blockable_check == other.white_blockable_check
+            && *black_blockable_check == other.black_blockable_check
+    }
+}
+
+/// Attack bitboards of both colors, computed on first access.
+#[derive(Default, Clone, Debug)]
+struct LazyAttacks {
+    white: OnceCell<Bitboard>,
+    black: OnceCell<Bitboard>,
+}
+
+pub(crate) const KING_DIRECTIONS: [(i8, i8); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+
+/// Squares attacked by a pawn, indexed by `[color as usize][position.board_index()]`
+pub const PAWN_ATTACKS: [[Bitboard; 64]; 2] = generate_pawn_attacks();
+
+pub(crate) const KNIGHT_JUMPS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+
+/// Squares attacked by a knight, indexed by `position.board_index()`
+pub const KNIGHT_ATTACKS: [Bitboard; 64] = generate_jump_attacks(KNIGHT_JUMPS);
+
+/// Squares attacked by a king, indexed by `position.board_index()`
+pub const KING_ATTACKS: [Bitboard; 64] = generate_jump_attacks(KING_DIRECTIONS);
+
+/// Sets the target of every jump that stays on the board
+const fn generate_jump_attacks(jumps: [(i8, i8); 8]) -> [Bitboard; 64] {
+    let mut table = [Bitboard(0); 64];
+    let mut index = 0;
+    while index < 64 {
+        let (file, rank) = ((index % 8) as i8, (index / 8) as i8);
+        let mut jump = 0;
+        while jump < jumps.len() {
+            let (x, y) = (file + jumps[jump].0, rank + jumps[jump].1);
+            if x >= 0 && x < 8 && y >= 0 && y < 8 {
+                table[index].0 |= 1 << (y * 8 + x);
+            }
+            jump += 1;
+        }
+        index += 1;
+    }
+    table
+}
+
+const fn generate_pawn_attacks() -> [[Bitboard; 64]; 2] {
+    let not_file_a = !Bitboard::FILE_A.0;
+    let not_file_h = !Bitboard::FILE_H.0;
+    let mut table = [[Bitboard(0); 64]; 2];
+    let mut index = 0;
+    while index < 64 {
+        let pawn = 1u64 << index;
+        table[Color::White as usize][index] =
+            Bitboard(((pawn << 9) & not_file_a) | ((pawn << 7) & not_file_h));
+        table[Color::Black as usize][index] =
+            Bitboard(((pawn >> 7) & not_file_a) | ((pawn >> 9) & not_file_h));
+        index += 1;
+    }
+    table
+}
+
+const ROOK_OPEN_FILE_BONUS: i32 = 25;
+const ROOK_SEMI_OPEN_FILE_BONUS: i32 = 10;
+const LIGHT_SQUARES: u64 = 0x55AA55AA55AA55AA;
+const BISHOP_PAIR_BONUS: i32 = 30;
+const LONE_KNIGHT_PENALTY: i32 = 20;
+// With this many pawns or less the position is considered open
+const OPEN_POSITION_MAX_PAWNS: usize = 8;
+
 #[derive(Debug, Clone, Copy)]
 pub enum BitBoardPrinter {
     WhitePawns,
@@ -341,8 +544,8 @@ impl BoardPrinter for BitBoardPrinter {
             Self::BlackKing => Bitboard::from(game.bitboards().black_king),
             Self::WhitePieces => game.bitboards().white_pieces,
             Self::BlackPieces => game.bitboards().black_pieces,
-            Self::WhiteAttacks => game.bitboards().white_attacks,
-            Self::BlackAttacks => game.bitboards().black_attacks,
+            Self::WhiteAttacks => game.bitboards().attacks(Color::White),
+            Self::BlackAttacks => game.bitboards().attacks(Color::Black),
             Self::WhitePinned => game
                 .bitboards()
                 .white_pinned
@@ -378,12 +581,82 @@ impl BoardPrinter for BitBoardPrinter {
 pub struct Bitboard(u64);
 
 impl Bitboard {
+    pub const FILE_A: Self = Self(0x0101010101010101);
+    pub const FILE_B: Self = Self(0x0101010101010101 << 1);
+    pub const FILE_C: Self = Self(0x0101010101010101 << 2);
+    pub const FILE_D: Self = Self(0x0101010101010101 << 3);
+    pub const FILE_E: Self = Self(0x0101010101010101 << 4);
+    pub const FILE_F: Self = Self(0x0101010101010101 << 5);
+    pub const FILE_G: Self = Self(0x0101010101010101 << 6);
+    pub const FILE_H: Self = Self(0x0101010101010101 << 7);
+
+    pub const RANK_1: Self = Self(0xFF);
+    pub const RANK_2: Self = Self(0xFF << 8);
+    pub const RANK_3: Self = Self(0xFF << 16);
+    pub const RANK_4: Self = Self(0xFF << 24);
+    pub const RANK_5: Self = Self(0xFF << 32);
+    pub const RANK_6: Self = Self(0xFF << 40);
+    pub const RANK_7: Self = Self(0xFF << 48);
+    pub const RANK_8: Self = Self(0xFF << 56);
+
+    /// Returns the mask of the given file (0 = 'a')
+    /// # Panics
+    /// If the file is not in range 0..8
+    pub const fn file(file: u8) -> Self {
+        assert!(file < 8, "File out of range");
+        Self(Self::FILE_A.0 << file)
+    }
+
+    /// Returns the mask of the given rank (0 = rank 1)
+    /// # Panics
+    /// If the rank is not in range 0..8
+    pub const fn rank(rank: u8) -> Self {
+        assert!(rank < 8, "Rank out of range");
+        Self(Self::RANK_1.0 << (rank * 8))
+    }
+
+    /// Returns a bitboard with all the given positions set.
+    pub fn from_positions(positions: impl IntoIterator<Item = Position>) -> Bitboard {
+        positions
+            .into_iter()
+            .fold(Bitboard::default(), |bitboard, position| {
+                bitboard | Bitboard::from(position)
+            })
+    }
+
+    /// Returns the squares a pawn of the given color attacks from the position.
+    pub fn pawn_attacks(color: Color, position: &Position) -> Bitboard {
+        PAWN_ATTACKS[color as usize][position.board_index()]
+    }
+
+    /// Returns the squares a knight attacks from the position.
+    pub fn knight_attacks(position: &Position) -> Bitboard {
+        KNIGHT_ATTACKS[position.board_index()]
+    }
+
+    /// Returns the squares a king attacks from the position, without castling.
+    pub fn king_attacks(position: &Position) -> Bitboard {
+        KING_ATTACKS[position.board_index()]
+    }
+
     pub fn iter(&self) -> impl Iterator<Item = Position> + '_ {
-        (0..64).filter_map(|index| {
-            (self.0 & (1 << index) != 0).then_some(Position::from_board_index_unchecked(index))
+        // Pops the lowest set square until none is left
+        let mut remaining = self.0;
+        std::iter::from_fn(move || {
+            if remaining == 0 {
+                return None;
+            }
+            let index = remaining.trailing_zeros() as usize;
+            remaining &= remaining - 1;
+            Some(Position::from_board_index_unchecked(index))
         })
     }
 
+    /// Returns the number of set squares
+    pub fn count(&self) -> u32 {
+        self.0.count_ones()
+    }
+
     pub fn contains(&self, position: &Position) -> bool {
         self.0 & (1 << position.board_index()) != 0
     }
@@ -391,6 +664,59 @@ impl Bitboard {
     pub fn inner(&self) -> u64 {
         self.0
     }
+
+    // Directional shifts. Shifts along the files mask out the bits that would wrap around
+    // to the other side of the board.
+    pub fn shift_north(&self) -> Bitboard {
+        Self(self.0 << 8)
+    }
+
+    pub fn shift_south(&self) -> Bitboard {
+        Self(self.0 >> 8)
+    }
+
+    pub fn shift_east(&self) -> Bitboard {
+        Self((self.0 << 1) & !Self::FILE_A.0)
+    }
+
+    pub fn shift_west(&self) -> Bitboard {
+        Self((self.0 >> 1) & !Self::FILE_H.0)
+    }
+
+    pub fn shift_north_east(&self) -> Bitboard {
+        Self((self.0 << 9) & !Self::FILE_A.0)
+    }
+
+    pub fn shift_north_west(&self) -> Bitboard {
+        Self((self.0 << 7) & !Self::FILE_H.0)
+    }
+
+    pub fn shift_south_east(&self) -> Bitboard {
+        Self((self.0 >> 7) & !Self::FILE_A.0)
+    }
+
+    pub fn shift_south_west(&self) -> Bitboard {
+        Self((self.0 >> 9) & !Self::FILE_H.0)
+    }
+
+    /// Fills every square north of each set bit, including the bit itself.
+    /// Useful for pawn spans (front spans, passed pawn detection).
+    pub fn north_fill(&self) -> Bitboard {
+        let mut fill = self.0;
+        fill |= fill << 8;
+        fill |= fill << 16;
+        fill |= fill << 32;
+        Self(fill)
+    }
+
+    /// Fills every square south of each set bit, including the bit itself.
+    pub fn south_fill(&self) -> Bitboard {
+        let mut fill = self.0;
+        fill |= fill >> 8;
+        fill |= fill >> 16;
+        fill |= fill >> 32;
+        Self(fill)
+    }
 }
 
 impl From<u64> for Bitboard {
@@ -461,20 +787,120 @@ impl BitAndAssign<Bitboard> for Bitboard {
     }
 }
 
+impl BitXor<Bitboard> for Bitboard {
+    type Output = Self;
+
+    fn bitxor(self, rhs: Self) -> Self::Output {
+        Self::from(self.0 ^ rhs.0)
+    }
+}
+
+impl BitXorAssign<Bitboard> for Bitboard {
+    fn bitxor_assign(&mut self, rhs: Bitboard) {
+        self.0 ^= rhs.0;
+    }
+}
+
+/// Set difference. All bits of `self` that are not set in `rhs`
+impl Sub<Bitboard> for Bitboard {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self::Output {
+        Self::from(self.0 & !rhs.0)
+    }
+}
+
 impl Not for Bitboard {
-    type Output = u64;
+    type Output = Self;
 
     fn not(self) -> Self::Output {
-        !self.0
+        Self::from(!self.0)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::fen::Fen;
+    use crate::{fen::Fen, move_generation::MoveGenerator, moves::MoveType};
 
     use super::*;
 
+    #[test]
+    fn pawn_attack_table() {
+        for color in [Color::White, Color::Black] {
+            for index in 0..64 {
+                let position = Position::from_board_index_unchecked(index);
+                let mut expected = Bitboard::default();
+                for dx in [-1, 1] {
+                    if let Some(attack) = position.offset(dx, color.board_direction()) {
+                        expected |= Bitboard::from(attack);
+                    }
+                }
+                assert_eq!(Bitboard::pawn_attacks(color, &position), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn knight_attack_table() {
+        for index in 0..64 {
+            let position = Position::from_board_index_unchecked(index);
+            let mut expected = Bitboard::default();
+            for dx in [-2i8, -1, 1, 2] {
+                for dy in [-2i8, -1, 1, 2] {
+                    if dx.abs() == dy.abs() {
+                        continue;
+                    }
+                    if let Some(attack) = position.offset(dx, dy) {
+                        expected |= Bitboard::from(attack);
+                    }
+                }
+            }
+            assert_eq!(Bitboard::knight_attacks(&position), expected, "{position}");
+        }
+        assert_eq!(Bitboard::knight_attacks(&Position::A1).count(), 2);
+        assert_eq!(Bitboard::knight_attacks(&Position::D4).count(), 8);
+    }
+
+    #[test]
+    fn king_attack_table() {
+        for index in 0..64 {
+            let position = Position::from_board_index_unchecked(index);
+            let mut expected = Bitboard::default();
+            for dx in [-1, 0, 1] {
+                for dy in [-1, 0, 1] {
+                    if let Some(attack) = position.offset(dx, dy).filter(|&to| to != position) {
+                        expected |= Bitboard::from(attack);
+                    }
+                }
+            }
+            assert_eq!(Bitboard::king_attacks(&position), expected, "{position}");
+        }
+        for (position, count) in [
+            (Position::A1, 3),
+            (Position::H8, 3),
+            (Position::A5, 5),
+            (Position::E1, 5),
+            (Position::E4, 8),
+        ] {
+            assert_eq!(
+                Bitboard::king_attacks(&position).count(),
+                count,
+                "{position}"
+            );
+        }
+    }
+
+    #[test]
+    fn checkers() {
+        // Double check by knight and rook
+        let game = Fen::parse_game("4k3/8/3N4/8/8/8/8/4R1K1 b - - 0 1").unwrap();
+        let checkers = game.bitboards().checkers(Color::Black);
+        assert_eq!(checkers.iter().count(), 2);
+        assert!(checkers.contains(&Position::D6));
+        assert!(checkers.contains(&Position::E1));
+        assert_eq!(game.bitboards().checkers(Color::White), Bitboard::default());
+    }
+
     #[test]
     fn test_iter() {
         let bitboard = Bitboard(0b1010101010101010);
@@ -587,9 +1013,113 @@ mod tests {
         let bitboard = Bitboard(0b1010101010101010);
         let result = !bitboard;
         assert_eq!(
-            result,
+            result.inner(),
             0b1111111111111111111111111111111111111111111111110101010101010101
         );
+        assert_eq!(!result, bitboard);
+    }
+
+    #[test]
+    fn test_bitxor() {
+        let bitboard = Bitboard(0b1010);
+        let toggle = Bitboard(0b0110);
+        assert_eq!((bitboard ^ toggle).inner(), 0b1100);
+
+        let mut toggled = bitboard;
+        toggled ^= toggle;
+        assert_eq!(toggled.inner(), 0b1100);
+        toggled ^= toggle;
+        assert_eq!(toggled, bitboard);
+    }
+
+    #[test]
+    fn test_sub() {
+        let bitboard = Bitboard(0b1110);
+        assert_eq!((bitboard - Bitboard(0b0110)).inner(), 0b1000);
+        assert_eq!((bitboard - bitboard).inner(), 0);
+        assert_eq!(bitboard - Bitboard::default(), bitboard);
+    }
+
+    #[test]
+    fn test_shift_north_south() {
+        let bitboard = Bitboard::from(Position::E4);
+        assert_eq!(bitboard.shift_north(), Bitboard::from(Position::E5));
+        assert_eq!(bitboard.shift_south(), Bitboard::from(Position::E3));
+
+        // Shifting off the board removes the bit
+        assert_eq!(Bitboard::from(Position::E8).shift_north().inner(), 0);
+        assert_eq!(Bitboard::from(Position::E1).shift_south().inner(), 0);
+    }
+
+    #[test]
+    fn test_shift_east_west() {
+        let bitboard = Bitboard::from(Position::E4);
+        assert_eq!(bitboard.shift_east(), Bitboard::from(Position::F4));
+        assert_eq!(bitboard.shift_west(), Bitboard::from(Position::D4));
+        assert_eq!(bitboard.shift_north_east(), Bitboard::from(Position::F5));
+        assert_eq!(bitboard.shift_north_west(), Bitboard::from(Position::D5));
+        assert_eq!(bitboard.shift_south_east(), Bitboard::from(Position::F3));
+        assert_eq!(bitboard.shift_south_west(), Bitboard::from(Position::D3));
+    }
+
+    #[test]
+    fn test_shift_no_wrap() {
+        // Pawns on the h file must not wrap to the a file
+        let h_file = Bitboard::from(Position::H4);
+        assert_eq!(h_file.shift_east().inner(), 0);
+        assert_eq!(h_file.shift_north_east().inner(), 0);
+        assert_eq!(h_file.shift_south_east().inner(), 0);
+        assert_eq!(h_file.shift_north_west(), Bitboard::from(Position::G5));
+
+        // Pawns on the a file must not wrap to the h file
+        let a_file = Bitboard::from(Position::A4);
+        assert_eq!(a_file.shift_west().inner(), 0);
+        assert_eq!(a_file.shift_north_west().inner(), 0);
+        assert_eq!(a_file.shift_south_west().inner(), 0);
+        assert_eq!(a_file.shift_north_east(), Bitboard::from(Position::B5));
+    }
+
+    #[test]
+    fn test_fills() {
+        let bitboard = Bitboard::from(Position::C3);
+        let north = bitboard.north_fill();
+        assert_eq!(north.iter().count(), 6);
+        assert!(north.contains(&Position::C3));
+        assert!(north.contains(&Position::C8));
+        assert!(!north.contains(&Position::C2));
+
+        let south = bitboard.south_fill();
+        assert_eq!(south.iter().count(), 3);
+        assert!(south.contains(&Position::C1));
+        assert!(!south.contains(&Position::C4));
+    }
+
+    #[test]
+    fn test_file_masks() {
+        assert_eq!(Bitboard::FILE_A.iter().count(), 8);
+        assert!(Bitboard::FILE_A.iter().all(|p| p.file() == 0));
+        assert!(Bitboard::FILE_H.iter().all(|p| p.file() == 7));
+
+        for file in 0..8 {
+            let mask = Bitboard::file(file);
+            assert_eq!(mask.iter().count(), 8);
+            assert!(mask.iter().all(|p| p.file() == file));
+        }
+        assert_eq!(Bitboard::file(4), Bitboard::FILE_E);
+    }
+
+    #[test]
+    fn test_rank_masks() {
+        assert_eq!(Bitboard::RANK_1.iter().count(), 8);
+        assert!(Bitboard::RANK_1.iter().all(|p| p.rank() == 0));
+        assert!(Bitboard::RANK_8.iter().all(|p| p.rank() == 7));
+
+        for rank in 0..8 {
+            let mask = Bitboard::rank(rank);
+            assert_eq!(mask.iter().count(), 8);
+            assert!(mask.iter().all(|p| p.rank() == rank));
+        }
+        assert_eq!(Bitboard::rank(3), Bitboard::RANK_4);
     }
 
     #[test]
@@ -618,4 +1148,69 @@ mod tests {
             0b1111111111111111 << 48
         );
     }
+
+    #[test]
+    fn lazy_attacks_equal_eager_ones() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            // Sliders attack through the king
+            "8/8/8/8/r3K3/8/8/4k3 w - - 0 1",
+        ] {
+            let game = Fen::parse_game(fen).unwrap();
+            let move_generator = MoveGenerator::new(&game);
+            for color in [Color::White, Color::Black] {
+                // Attacks of every piece, as the move generator sees them
+                let mut eager = Bitboard::default();
+                for position in game.bitboards().pieces(color).iter() {
+                    for attack in move_generator.possible_attacking_moves(&position) {
+                        if attack.move_type != MoveType::Castle {
+                            eager |= Bitboard::from(attack.to);
+                        }
+                    }
+                }
+                assert_eq!(game.bitboards().attacks(color), eager, "{fen} {color:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn comparing_ignores_cached_attacks() {
+        let game = Game::default();
+        let fresh = GameBitBoards::new(&game);
+        fresh.attacks(Color::White);
+        assert_eq!(&fresh, game.bitboards());
+
+        let mut moved = fresh.clone();
+        moved.white_pawns ^= Bitboard::from(Position::E2) | Bitboard::from(Position::E4);
+        assert_ne!(moved, fresh);
+    }
+
+    #[test]
+    fn pinned_pieces() {
+        // Knight pinned on the rank, bishop pinned on the file
+        let game = Fen::parse_game("7k/8/8/8/r1N1K3/8/4B3/4r3 w - - 0 1").unwrap();
+        let pinned = game.bitboards().pinned_pieces(Color::White);
+        assert_eq!(
+            pinned,
+            Bitboard::from_positions([Position::C4, Position::E2])
+        );
+        assert_eq!(
+            game.bitboards().pinned_pieces(Color::Black),
+            Bitboard::default()
+        );
+    }
+
+    #[test]
+    fn test_from_positions() {
+        let positions = [Position::A1, Position::E4, Position::H8];
+        let bitboard = Bitboard::from_positions(positions);
+        assert_eq!(bitboard.count(), 3);
+        for position in positions {
+            assert!(bitboard.contains(&position));
+        }
+        assert_eq!(Bitboard::from_positions([]), Bitboard::default());
+    }
 }
@@ -67,6 +67,13 @@ impl Board {
             (position, piece.as_ref())
         })
     }
+
+    /// Iterates over the occupied squares only, starting at a1.
+    pub fn iter_pieces(&self) -> impl Iterator<Item = (Position, Piece)> + '_ {
+        self.0.iter().enumerate().filter_map(|(i, piece)| {
+            piece.map(|piece| (Position::from_board_index_unchecked(i), piece))
+        })
+    }
 }
 
 #[cfg(test)]
@@ -100,4 +107,17 @@ mod tests {
         let pos = Position::new_unchecked(8, 8);
         board.piece_at(&pos);
     }
+
+    #[test]
+    fn iter_pieces_skips_empty_squares() {
+        assert_eq!(Board::default().iter_pieces().count(), 0);
+
+        let game = crate::game::Game::default();
+        let pieces = game.board().iter_pieces().collect::<Vec<_>>();
+        assert_eq!(pieces.len(), 32);
+        assert_eq!(
+            pieces[0],
+            (Position::A1, Piece::new(PieceType::Rook, Color::White))
+        );
+    }
 }
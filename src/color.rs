@@ -32,6 +32,14 @@ impl Color {
             Self::Black => -1,
         }
     }
+
+    /// Sign of scores seen from white, `1` for white and `-1` for black.
+    pub fn sign(&self) -> i32 {
+        match self {
+            Self::White => 1,
+            Self::Black => -1,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -55,4 +63,10 @@ mod tests {
         assert_eq!(Color::White.board_direction(), 1);
         assert_eq!(Color::Black.board_direction(), -1);
     }
+
+    #[test]
+    fn sign() {
+        assert_eq!(Color::White.sign(), 1);
+        assert_eq!(Color::Black.sign(), -1);
+    }
 }
@@ -0,0 +1,98 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+
+use crate::{fen::Fen, game::Game};
+
+/// Extended position description, as used by engine test suites.
+/// The first four fields of a fen followed by operations, e.g. `bm Qg6; id "WAC.001";`
+pub struct Epd;
+
+impl Epd {
+    /// Returns the position and its operations by opcode.
+    /// The operands of an operation are kept as one string, quotes around them are removed.
+    pub fn parse(epd: &str) -> anyhow::Result<(Game, HashMap<String, String>)> {
+        let mut rest = epd.trim();
+        let mut position = Vec::new();
+        for _ in 0..4 {
+            let (field, remaining) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            if field.is_empty() {
+                anyhow::bail!(
+                    "An epd needs placement, side to move, castling and en passant field"
+                );
+            }
+            position.push(field);
+            rest = remaining.trim_start();
+        }
+        let game = Fen::parse_game(&position.join(" ")).context("Invalid epd position")?;
+
+        let operations = Self::split_operations(rest)
+            .into_iter()
+            .map(|operation| {
+                let (opcode, operands) = operation.split_once(' ').unwrap_or((operation, ""));
+                let operands = operands.trim();
+                let operands = operands
+                    .strip_prefix('"')
+                    .and_then(|operands| operands.strip_suffix('"'))
+                    .unwrap_or(operands);
+                (opcode.to_string(), operands.to_string())
+            })
+            .collect();
+        Ok((game, operations))
+    }
+
+    // Splits at the semicolons outside of quoted strings
+    fn split_operations(operations: &str) -> Vec<&str> {
+        let mut result = Vec::new();
+        let mut in_quotes = false;
+        let mut start = 0;
+        for (index, c) in operations.char_indices() {
+            match c {
+                '"' => in_quotes = !in_quotes,
+                ';' if !in_quotes => {
+                    result.push(operations[start..index].trim());
+                    start = index + 1;
+                }
+                _ => {}
+            }
+        }
+        result.push(operations[start..].trim());
+        result.retain(|operation| !operation.is_empty());
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{color::Color, piece::Piece, piece_type::PieceType, position::Position};
+
+    #[test]
+    fn parse_wac_line() {
+        let (game, operations) = Epd::parse(
+            "2rr3k/pp3pp1/1nnqbN1p/3pN3/2pP4/2P3Q1/PPB4P/R4RK1 w - - bm Qg6; id \"WAC.001\";",
+        )
+        .unwrap();
+        assert_eq!(game.current_turn(), Color::White);
+        assert_eq!(
+            game.board().piece_at(&Position::G3),
+            Some(&Piece::new(PieceType::Queen, Color::White))
+        );
+        assert_eq!(operations.len(), 2);
+        assert_eq!(operations["bm"], "Qg6");
+        assert_eq!(operations["id"], "WAC.001");
+    }
+
+    #[test]
+    fn parse_operations() {
+        let (_, operations) =
+            Epd::parse("4k3/8/8/8/8/8/8/4K3  b - -  bm Kd7 Ke7;c0 \"a; b\"; noop").unwrap();
+        assert_eq!(operations["bm"], "Kd7 Ke7");
+        assert_eq!(operations["c0"], "a; b");
+        assert_eq!(operations["noop"], "");
+
+        let (_, operations) = Epd::parse("4k3/8/8/8/8/8/8/4K3 b - -").unwrap();
+        assert!(operations.is_empty());
+        assert!(Epd::parse("4k3/8/8/8/8/8/8/4K3 b -").is_err());
+    }
+}
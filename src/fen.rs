@@ -1,6 +1,4 @@
-use std::str::FromStr;
-
-use anyhow::Context;
+use std::{fmt::Display, str::FromStr};
 
 use crate::{
     board::Board,
@@ -14,26 +12,51 @@ use crate::{
 pub struct Fen;
 
 impl Fen {
+    /// Returns the standard fen of the game.
+    /// The en passant field is only written if a legal en passant capture is possible,
+    /// the counters are the ones of [Game::halfmove_clock] and [Game::fullmove_number].
     pub fn from_game(game: &Game) -> String {
+        let en_passent = game
+            .en_passent_field()
+            .filter(|_| !game.en_passant_capturers().is_empty());
+        Self::write(
+            game,
+            en_passent,
+            game.halfmove_clock(),
+            game.fullmove_number(),
+        )
+    }
+
+    /// Returns the fen with the en passant field as stored in the game, even if nobody can capture.
+    /// The counters are the same as in [Fen::from_game]. Meant to debug the internal state.
+    pub fn from_game_raw(game: &Game) -> String {
+        Self::write(
+            game,
+            game.en_passent_field(),
+            game.halfmove_clock(),
+            game.fullmove_number(),
+        )
+    }
+
+    fn write(game: &Game, en_passent: Option<Position>, halfmove: u32, fullmove: u32) -> String {
         let mut fen = String::new();
 
         for row in (0..8).rev() {
-            let mut empty = 0;
-            for col in 0..8 {
-                let position = Position::new(col, row).unwrap();
-                let piece = game.board().piece_at(&position);
-                if let Some(piece) = piece {
-                    if empty > 0 {
-                        fen.push_str(&empty.to_string());
-                        empty = 0;
-                    }
-                    fen.push(piece.get_print_char());
-                } else {
-                    empty += 1;
+            let mut file = 0;
+            for (position, piece) in game
+                .board()
+                .iter_pieces()
+                .filter(|(position, _)| position.rank() == row)
+            {
+                let empty = position.file() - file;
+                if empty > 0 {
+                    fen.push_str(&empty.to_string());
                 }
+                fen.push(piece.get_print_char());
+                file = position.file() + 1;
             }
-            if empty > 0 {
-                fen.push_str(&empty.to_string());
+            if file < 8 {
+                fen.push_str(&(8 - file).to_string());
             }
             if row > 0 {
                 fen.push('/');
@@ -49,22 +72,12 @@ impl Fen {
 
         fen.push(' ');
 
-        let white_castling = game.white_castle_rights();
-        let black_castling = game.black_castle_rights();
-        match (white_castling, black_castling) {
-            (CastleRights::None, CastleRights::None) => fen.push('-'),
-            (CastleRights::None, black) => fen.push_str(black.to_string(Color::Black)),
-            (white, CastleRights::None) => fen.push_str(white.to_string(Color::White)),
-            (white, black) => {
-                fen.push_str(white.to_string(Color::White));
-                fen.push_str(black.to_string(Color::Black));
-            }
-        }
+        fen.push_str(&game.castling_fen());
 
         fen.push(' ');
 
         fen.push_str(
-            game.en_passent_field()
+            en_passent
                 .map(|p| p.to_string())
                 .unwrap_or("-".to_string())
                 .as_str(),
@@ -72,120 +85,155 @@ impl Fen {
 
         fen.push(' ');
 
-        fen.push('0');
+        fen.push_str(&halfmove.to_string());
 
         fen.push(' ');
 
-        fen.push('1');
+        fen.push_str(&fullmove.to_string());
 
         fen
     }
 
-    pub fn parse_game(fen: &str) -> anyhow::Result<Game> {
+    pub fn parse_game(fen: &str) -> Result<Game, FenError> {
         let mut part_iter = fen.split_whitespace();
 
-        let board = part_iter
-            .next()
-            .map(|position_part| {
-                let mut board = Board::default();
-                // We start at the top of the board
-                let mut row = 7;
-                let mut col = 0;
-                for c in position_part.chars() {
-                    if c.is_numeric() {
-                        let skip = c.to_digit(10).unwrap() as u8;
-                        col += skip;
-                    } else if c == '/' {
-                        row -= 1;
-                        col = 0;
-                    } else {
-                        let piece = match c {
-                            'P' => Piece::new(PieceType::Pawn, Color::White),
-                            'p' => Piece::new(PieceType::Pawn, Color::Black),
-                            'N' => Piece::new(PieceType::Knight, Color::White),
-                            'n' => Piece::new(PieceType::Knight, Color::Black),
-                            'B' => Piece::new(PieceType::Bishop, Color::White),
-                            'b' => Piece::new(PieceType::Bishop, Color::Black),
-                            'R' => Piece::new(PieceType::Rook, Color::White),
-                            'r' => Piece::new(PieceType::Rook, Color::Black),
-                            'Q' => Piece::new(PieceType::Queen, Color::White),
-                            'q' => Piece::new(PieceType::Queen, Color::Black),
-                            'K' => Piece::new(PieceType::King, Color::White),
-                            'k' => Piece::new(PieceType::King, Color::Black),
-                            c => anyhow::bail!("Failed to parse fen. Unknown piece {c}"),
-                        };
-                        board.place_piece(
-                            piece,
-                            &Position::new(col, row).context(format!(
-                                "Failed parsing fen. Position is getting out of bounds ({col}, {row})"
-                            ))?,
-                        );
-                        col += 1;
-                    }
-                }
-                Ok(board)
-            })
-            .context("No positions defined in the fen")??;
-
-        let turn_color = part_iter
-            .next()
-            .map(|turn| match turn {
-                "w" | "W" => Ok(Color::White),
-                "b" | "B" => Ok(Color::Black),
-                _ => {
-                    anyhow::bail!(
-                        "Failed to parse fen. Turn color could not be determinated. '{turn}'"
-                    )
-                }
-            })
-            .unwrap_or(Ok(Color::White))?;
-
-        let (castle_white, castle_black) = part_iter
-            .next()
-            .map(|castle_rights| {
-                let mut white_castle_rights = CastleRights::None;
-                let mut black_castle_rights = CastleRights::None;
-                for c in castle_rights.chars() {
-                    match c {
-                        'K' => white_castle_rights |= CastleRights::KingSide,
-                        'Q' => white_castle_rights |= CastleRights::QueenSide,
-                        'k' => black_castle_rights |= CastleRights::KingSide,
-                        'q' => black_castle_rights |= CastleRights::QueenSide,
-                        '-' => (),
-                        _ => anyhow::bail!("Failed to parse fen. Unknown castle right {c}"),
-                    }
-                }
-                Ok((white_castle_rights, black_castle_rights))
-            })
-            .unwrap_or(Ok((CastleRights::None, CastleRights::None)))?;
-
-        let en_passent_field = part_iter
-            .next()
-            .map(|en_passent_field| {
-                if en_passent_field == "-" {
-                    Ok(None)
-                } else {
-                    Position::from_str(en_passent_field).map(Some)
-                }
+        let board = Self::parse_placement(part_iter.next().ok_or(FenError::MissingPlacement)?)?;
+
+        let turn_color = match part_iter.next() {
+            None | Some("w" | "W") => Color::White,
+            Some("b" | "B") => Color::Black,
+            Some(_) => return Err(FenError::BadSideToMove),
+        };
+
+        let mut castle_white = CastleRights::None;
+        let mut castle_black = CastleRights::None;
+        for c in part_iter.next().unwrap_or("-").chars() {
+            match c {
+                'K' => castle_white |= CastleRights::KingSide,
+                'Q' => castle_white |= CastleRights::QueenSide,
+                'k' => castle_black |= CastleRights::KingSide,
+                'q' => castle_black |= CastleRights::QueenSide,
+                '-' => (),
+                c => return Err(FenError::BadCastle(c)),
+            }
+        }
+
+        let en_passent_field = match part_iter.next() {
+            None | Some("-") => None,
+            Some(field) => Some(Position::from_str(field).map_err(|_| FenError::BadEnPassant)?),
+        };
+
+        // The counters are optional, but have to be numbers if given
+        let mut counter = |default| {
+            part_iter.next().map_or(Ok(default), |counter| {
+                counter.parse::<u32>().map_err(|_| FenError::BadCounter)
             })
-            .unwrap_or(Ok(None))?;
+        };
+        let halfmove_clock = counter(0)?;
+        let fullmove_number = counter(1)?;
 
-        Ok(Game::new(
+        let mut game = Game::new(
             board,
             turn_color,
             castle_white,
             castle_black,
             en_passent_field,
-        ))
+        );
+        game.set_move_counters(halfmove_clock, fullmove_number);
+        Ok(game)
+    }
+
+    fn parse_placement(placement: &str) -> Result<Board, FenError> {
+        let mut board = Board::default();
+        // We start at the top of the board
+        let mut row = 7;
+        let mut col = 0;
+        for c in placement.chars() {
+            if let Some(skip) = c.to_digit(10) {
+                col += skip as u8;
+                if col > 8 {
+                    return Err(FenError::OutOfBounds);
+                }
+            } else if c == '/' {
+                if col != 8 {
+                    return Err(FenError::IncompletePlacement);
+                }
+                if row == 0 {
+                    return Err(FenError::OutOfBounds);
+                }
+                row -= 1;
+                col = 0;
+            } else {
+                let piece = match c {
+                    'P' => Piece::new(PieceType::Pawn, Color::White),
+                    'p' => Piece::new(PieceType::Pawn, Color::Black),
+                    'N' => Piece::new(PieceType::Knight, Color::White),
+                    'n' => Piece::new(PieceType::Knight, Color::Black),
+                    'B' => Piece::new(PieceType::Bishop, Color::White),
+                    'b' => Piece::new(PieceType::Bishop, Color::Black),
+                    'R' => Piece::new(PieceType::Rook, Color::White),
+                    'r' => Piece::new(PieceType::Rook, Color::Black),
+                    'Q' => Piece::new(PieceType::Queen, Color::White),
+                    'q' => Piece::new(PieceType::Queen, Color::Black),
+                    'K' => Piece::new(PieceType::King, Color::White),
+                    'k' => Piece::new(PieceType::King, Color::Black),
+                    c => return Err(FenError::BadPiece(c)),
+                };
+                board.place_piece(
+                    piece,
+                    &Position::new(col, row).ok_or(FenError::OutOfBounds)?,
+                );
+                col += 1;
+            }
+        }
+        if row != 0 || col != 8 {
+            return Err(FenError::IncompletePlacement);
+        }
+        Ok(board)
+    }
+}
+
+/// Why a fen could not be parsed. Converts into `anyhow::Error` with `?`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FenError {
+    /// The fen is empty
+    MissingPlacement,
+    BadPiece(char),
+    /// A rank has more than 8 files or there are more than 8 ranks
+    OutOfBounds,
+    /// A rank has less than 8 files or there are less than 8 ranks
+    IncompletePlacement,
+    BadSideToMove,
+    BadCastle(char),
+    BadEnPassant,
+    /// The halfmove clock or fullmove number is no number
+    BadCounter,
+}
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FenError::MissingPlacement => write!(f, "No positions defined in the fen"),
+            FenError::BadPiece(c) => write!(f, "Unknown piece {c}"),
+            FenError::OutOfBounds => write!(f, "Pieces are placed out of the board"),
+            FenError::IncompletePlacement => write!(f, "Not all 64 fields are given"),
+            FenError::BadSideToMove => write!(f, "Turn color could not be determinated"),
+            FenError::BadCastle(c) => write!(f, "Unknown castle right {c}"),
+            FenError::BadEnPassant => write!(f, "Invalid en passant field"),
+            FenError::BadCounter => write!(f, "Move counters have to be numbers"),
+        }
     }
 }
 
+impl std::error::Error for FenError {}
+
 #[cfg(test)]
 mod tests {
-    use super::Fen;
+    use super::{Fen, FenError};
     use crate::board::Board;
     use crate::color::Color;
     use crate::game::{CastleRights, Game};
+    use crate::moves::{Move, MoveType};
     use crate::piece::Piece;
     use crate::piece_type::PieceType;
     use crate::position::Position;
@@ -205,6 +253,21 @@ mod tests {
         assert!(game.is_err());
     }
 
+    #[test]
+    fn test_invalid_rank_count() {
+        assert!(Fen::parse_game("8/8/8 w - - 0 1").is_err());
+        assert!(Fen::parse_game("8/8/8/8/8/8/8/8/8 w - - 0 1").is_err());
+    }
+
+    #[test]
+    fn test_invalid_file_count() {
+        // Rank overflows by skipping
+        assert!(Fen::parse_game("4k3/8/8/8/45/8/8/4K3 w - - 0 1").is_err());
+        // Rank is too short
+        assert!(Fen::parse_game("4k3/8/8/8/7/8/8/4K3 w - - 0 1").is_err());
+        assert!(Fen::parse_game("4k3/8/8/8/8/8/8/4K2 w - - 0 1").is_err());
+    }
+
     #[test]
     fn test_invalid_piece() {
         // Test what happens when we have an invalid piece
@@ -368,6 +431,24 @@ mod tests {
         assert_eq!(fen, parse_fen);
     }
 
+    #[test]
+    fn test_to_fen_partial_castle_rights() {
+        // Every combination of white and black rights, both kings share the e file
+        for white in ["", "K", "Q", "KQ"] {
+            for black in ["", "k", "q", "kq"] {
+                let castling = format!("{white}{black}");
+                let castling = if castling.is_empty() { "-" } else { &castling };
+                let parse_fen = format!("r3k2r/8/8/8/8/8/8/R3K2R w {castling} - 0 1");
+                let game = Fen::parse_game(&parse_fen).unwrap();
+                assert_eq!(Fen::from_game(&game), parse_fen);
+            }
+        }
+
+        // Non standard ordering is normalized
+        let game = Fen::parse_game("r3k2r/8/8/8/8/8/8/R3K2R w qK - 0 1").unwrap();
+        assert_eq!(Fen::from_game(&game), "r3k2r/8/8/8/8/8/8/R3K2R w Kq - 0 1");
+    }
+
     #[test]
     fn unknown_turn_color() {
         let game = Fen::parse_game("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1");
@@ -379,4 +460,104 @@ mod tests {
         let game = Fen::parse_game("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w x - 0 1");
         assert!(game.is_err());
     }
+
+    #[test]
+    fn error_variants() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR";
+        for (fen, error) in [
+            ("", FenError::MissingPlacement),
+            ("  ", FenError::MissingPlacement),
+            ("abc5/8/8/8/8/8/8/8 w - - 0 1", FenError::BadPiece('a')),
+            ("rnbqk3r/8/8/8/8/8/8/8 w - - 0 1", FenError::OutOfBounds),
+            ("4k3/8/8/8/45/8/8/4K3 w - - 0 1", FenError::OutOfBounds),
+            ("8/8/8/8/8/8/8/8/8 w - - 0 1", FenError::OutOfBounds),
+            ("8/8/8 w - - 0 1", FenError::IncompletePlacement),
+            (
+                "4k3/8/8/8/7/8/8/4K3 w - - 0 1",
+                FenError::IncompletePlacement,
+            ),
+            (&format!("{start} x KQkq - 0 1"), FenError::BadSideToMove),
+            (&format!("{start} w KQxq - 0 1"), FenError::BadCastle('x')),
+            (&format!("{start} w KQkq e9 0 1"), FenError::BadEnPassant),
+            (&format!("{start} w KQkq - x 1"), FenError::BadCounter),
+            (&format!("{start} w KQkq - 0 -1"), FenError::BadCounter),
+        ] {
+            assert_eq!(Fen::parse_game(fen).unwrap_err(), error, "{fen}");
+        }
+
+        // Converts into anyhow errors
+        let error: anyhow::Error = Fen::parse_game("9/8").unwrap_err().into();
+        assert_eq!(error.to_string(), "Pieces are placed out of the board");
+        assert!(Fen::parse_game(start).is_ok());
+    }
+
+    #[test]
+    fn test_to_fen_en_passant_and_counters() {
+        let mut game = Game::default();
+        game.make_move(Move::new(
+            Position::E2,
+            Position::E4,
+            MoveType::DoublePawnPush(Position::E3),
+        ))
+        .unwrap();
+        // No black pawn can take on e3
+        assert_eq!(
+            Fen::from_game(&game),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1"
+        );
+        assert_eq!(
+            Fen::from_game_raw(&game),
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1"
+        );
+
+        let mut game = Fen::parse_game("4k3/8/8/8/5p2/8/4P3/4K3 w - - 0 1").unwrap();
+        game.make_moves([
+            Move::new(Position::E1, Position::D1, MoveType::Quiet),
+            Move::new(Position::E8, Position::D8, MoveType::Quiet),
+            Move::new(
+                Position::E2,
+                Position::E4,
+                MoveType::DoublePawnPush(Position::E3),
+            ),
+        ])
+        .unwrap();
+        assert_eq!(Fen::from_game(&game), "3k4/8/8/8/4Pp2/8/8/3K4 b - e3 0 2");
+        assert_eq!(
+            Fen::from_game_raw(&game),
+            "3k4/8/8/8/4Pp2/8/8/3K4 b - e3 0 2"
+        );
+
+        game.make_move(Move::new(Position::D8, Position::C8, MoveType::Quiet))
+            .unwrap();
+        assert_eq!(Fen::from_game(&game), "2k5/8/8/8/4Pp2/8/8/3K4 w - - 1 3");
+    }
+
+    #[test]
+    fn counters_round_trip() {
+        let fen = "r3k2r/8/8/8/8/8/8/N3K2R b Kkq - 37 52";
+        let mut game = Fen::parse_game(fen).unwrap();
+        assert_eq!(game.halfmove_clock(), 37);
+        assert_eq!(game.fullmove_number(), 52);
+        assert_eq!(Fen::from_game(&game), fen);
+
+        game.make_move(Move::new(
+            Position::A8,
+            Position::A1,
+            MoveType::Capture(PieceType::Knight),
+        ))
+        .unwrap();
+        assert_eq!(Fen::from_game(&game), "4k2r/8/8/8/8/8/8/r3K2R w Kk - 0 53");
+        game.make_move(Move::new(Position::H1, Position::H7, MoveType::Quiet))
+            .unwrap();
+        assert_eq!(game.halfmove_clock(), 1);
+        assert_eq!(game.fullmove_number(), 53);
+
+        game.unmake_move();
+        game.unmake_move();
+        assert_eq!(Fen::from_game(&game), fen);
+
+        // Missing counters default to the start of a game
+        let game = Fen::parse_game("4k3/8/8/8/8/8/8/4K3 w - -").unwrap();
+        assert_eq!(Fen::from_game(&game), "4k3/8/8/8/8/8/8/4K3 w - - 0 1");
+    }
 }
@@ -1,27 +1,44 @@
-use std::ops::BitOrAssign;
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    io::{self, Write},
+    ops::BitOrAssign,
+    str::FromStr,
+};
+
+use anyhow::Context;
 
 use crate::{
-    bitboards::GameBitBoards,
+    bitboards::{Bitboard, GameBitBoards},
     board::Board,
     color::Color,
-    fen::Fen,
+    fen::{Fen, FenError},
+    move_generation::MoveGenerator,
     moves::{Move, MoveType},
     piece::Piece,
     piece_type::PieceType,
     position::Position,
     print_board::{BoardPrinter, DefaultBoardPrinter},
+    san::San,
+    search,
+    zobrist::Zobrist,
 };
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Game {
     current_turn: Color,
     board: Board,
-    move_stack: Vec<(Move, CastleRights, CastleRights, Option<Position>)>,
+    // Each move with the state needed to unmake it: castle rights, en passant field,
+    // halfmove clock and the hash of the position before the move
+    move_stack: Vec<(Move, CastleRights, CastleRights, Option<Position>, u32, u64)>,
     bitboards: GameBitBoards,
 
     white_castle_rights: CastleRights,
     black_castle_rights: CastleRights,
     en_passent_field: Option<Position>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    hash: u64,
 }
 
 impl Default for Game {
@@ -31,6 +48,22 @@ impl Default for Game {
     }
 }
 
+impl FromStr for Game {
+    type Err = FenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Fen::parse_game(s)
+    }
+}
+
+/// Displays the game as fen string.
+/// To render the board use [Game::print_pieces] or [Game::print_custom].
+impl Display for Game {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", Fen::from_game(self))
+    }
+}
+
 impl Game {
     pub fn new(
         board: Board,
@@ -47,11 +80,46 @@ impl Game {
             white_castle_rights,
             black_castle_rights,
             en_passent_field,
+            halfmove_clock: 0,
+            fullmove_number: 1,
+            hash: 0,
         };
         res.bitboards = GameBitBoards::new(&res);
+        res.recompute_hash();
         res
     }
 
+    /// Sets up a game from the pieces on their fields, e.g. from a position editor.
+    /// Fails unless both sides have exactly one king.
+    pub fn from_placement_map(
+        pieces: &HashMap<Position, Piece>,
+        current_turn: Color,
+        white_castle_rights: CastleRights,
+        black_castle_rights: CastleRights,
+        en_passent_field: Option<Position>,
+    ) -> anyhow::Result<Self> {
+        for color in [Color::White, Color::Black] {
+            let kings = pieces
+                .values()
+                .filter(|piece| **piece == Piece::new(PieceType::King, color))
+                .count();
+            if kings != 1 {
+                anyhow::bail!("{color:?} has {kings} kings.");
+            }
+        }
+        let mut board = Board::default();
+        for (position, piece) in pieces {
+            board.place_piece(*piece, position);
+        }
+        Ok(Self::new(
+            board,
+            current_turn,
+            white_castle_rights,
+            black_castle_rights,
+            en_passent_field,
+        ))
+    }
+
     /// Checks if the current moving player is in check
     /// # Returns
     /// `true` if the current moving player is in check, `false` otherwise.
@@ -63,6 +131,151 @@ impl Game {
         return opponent_moves.iter().any(|pos| pos == king_position);
     }
 
+    /// Returns the squares the color attacks, as used for checks and king safety.
+    /// Pawns only attack diagonally, the squares in front of them are not included.
+    /// Sliders attack through the enemy king, so the king can't step back along the ray.
+    pub fn attack_squares(&self, color: Color) -> Bitboard {
+        self.bitboards.attacks(color)
+    }
+
+    /// Returns how many pieces give check to the king of the side to move, at most 2.
+    /// On a double check only the king can move.
+    pub fn check_count(&self) -> u8 {
+        self.bitboards.blockable_checks(self.current_turn).len() as u8
+    }
+
+    /// Returns the result of the game, based on the current position.
+    pub fn result(&self) -> GameResult {
+        if !MoveGenerator::new(self)
+            .all_legal_moves(self.current_turn)
+            .is_empty()
+        {
+            if self.is_draw() {
+                return GameResult::Draw;
+            }
+            return GameResult::InProgress;
+        }
+        if !self.is_in_check() {
+            return GameResult::Draw;
+        }
+        match self.current_turn {
+            Color::White => GameResult::BlackWins,
+            Color::Black => GameResult::WhiteWins,
+        }
+    }
+
+    /// Returns the reason a draw can be claimed by the player to move.
+    /// Claimable after 50 moves without capture or pawn move, or on threefold repetition.
+    pub fn can_claim_draw(&self) -> Option<DrawReason> {
+        if self.halfmove_clock() >= 100 {
+            return Some(DrawReason::FiftyMoves);
+        }
+        if self.repetitions() >= 3 {
+            return Some(DrawReason::ThreefoldRepetition);
+        }
+        None
+    }
+
+    /// Checks for the automatic draws after 75 moves without capture or pawn move
+    /// and on fivefold repetition. These don't have to be claimed.
+    pub fn is_draw(&self) -> bool {
+        self.halfmove_clock() >= 150 || self.repetitions() >= 5
+    }
+
+    /// Heuristic for material that is usually drawn with correct play, e.g. rook against rook.
+    /// Conservative: any pawn on the board or a material advantage beyond these cases counts as not drawn.
+    pub fn is_likely_draw_material(&self) -> bool {
+        let [white, black] = [Color::White, Color::Black].map(|color| self.material(color));
+        if white[PieceType::Pawn as usize] + black[PieceType::Pawn as usize] > 0 {
+            return false;
+        }
+        let minors = |material: &[u8; 6]| {
+            material[PieceType::Knight as usize] + material[PieceType::Bishop as usize]
+        };
+        let heavies = |material: &[u8; 6]| {
+            material[PieceType::Rook as usize] + material[PieceType::Queen as usize]
+        };
+        let is_bare = |material: &[u8; 6]| minors(material) + heavies(material) == 0;
+        let two_knights = |material: &[u8; 6]| {
+            heavies(material) == 0
+                && material[PieceType::Bishop as usize] == 0
+                && material[PieceType::Knight as usize] == 2
+        };
+
+        // At most a minor piece each
+        if heavies(&white) + heavies(&black) == 0 && minors(&white) <= 1 && minors(&black) <= 1 {
+            return true;
+        }
+        // Two knights can't force mate
+        if (two_knights(&white) && is_bare(&black)) || (two_knights(&black) && is_bare(&white)) {
+            return true;
+        }
+        // A single rook or queen against the same piece
+        minors(&white) + minors(&black) == 0 && heavies(&white) == 1 && white == black
+    }
+
+    /// Returns the number of half moves since the last capture or pawn move.
+    /// Continues from the clock of a parsed fen.
+    pub fn halfmove_clock(&self) -> u32 {
+        self.halfmove_clock
+    }
+
+    /// Returns the number of the current full move, starting at 1 and increasing after black moved.
+    /// Continues from the move number of a parsed fen.
+    pub fn fullmove_number(&self) -> u32 {
+        self.fullmove_number
+    }
+
+    /// Sets the halfmove clock and the fullmove number, e.g. from the last fields of a fen.
+    pub fn set_move_counters(&mut self, halfmove_clock: u32, fullmove_number: u32) {
+        self.halfmove_clock = halfmove_clock;
+        self.fullmove_number = fullmove_number;
+    }
+
+    /// Returns a line with the state not visible on the board.
+    /// E.g. `White to move, castling KQkq, en passant -, halfmove clock 0, move 1`.
+    pub fn status_line(&self) -> String {
+        let en_passent = self
+            .en_passent_field
+            .map(|position| position.to_string())
+            .unwrap_or("-".to_string());
+        format!(
+            "{:?} to move, castling {}, en passant {}, halfmove clock {}, move {}",
+            self.current_turn,
+            self.castling_fen(),
+            en_passent,
+            self.halfmove_clock(),
+            self.fullmove_number()
+        )
+    }
+
+    // How often the current position occured in this game, including now
+    fn repetitions(&self) -> usize {
+        let hash = self.hash;
+        1 + self
+            .position_hashes()
+            .into_iter()
+            .filter(|&h| h == hash)
+            .count()
+    }
+
+    /// Returns the number of pieces the color has, indexed by [PieceType] (`PieceType::Pawn as usize` and so on).
+    pub fn material(&self, color: Color) -> [u8; 6] {
+        PieceType::ALL.map(|piece_type| self.bitboards.of_type(piece_type, color).count() as u8)
+    }
+
+    /// Returns the fields of all pieces with the given type and color, ordered by board index.
+    pub fn piece_positions(&self, piece_type: PieceType, color: Color) -> Vec<Position> {
+        self.bitboards.of_type(piece_type, color).iter().collect()
+    }
+
+    /// Returns one bitboard per piece giving check to the king of the given color.
+    /// Each contains the squares between the checker and the king, including the checker.
+    /// For knight and pawn checks only the checker itself is set.
+    pub fn king_attack_rays(&self, color: Color) -> Vec<Bitboard> {
+        self.bitboards.blockable_checks(color).to_vec()
+    }
+
     pub fn make_move(&mut self, mov: Move) -> anyhow::Result<()> {
         let Some(piece_to_move) = self.board.piece_at(&mov.from) else {
             anyhow::bail!("No piece to move at position {:?}", mov.from);
@@ -70,11 +283,58 @@ impl Game {
         if piece_to_move.color() != self.current_turn {
             anyhow::bail!("It's not {:?}'s turn to move.", piece_to_move.color());
         }
+        if mov.move_type != MoveType::Castle
+            && self
+                .board
+                .piece_at(&mov.to)
+                .is_some_and(|piece| piece.color() == self.current_turn)
+        {
+            anyhow::bail!("Can't capture own piece at position {:?}", mov.to);
+        }
+        // Checked before anything is changed, so a malformed castle move leaves the game untouched
+        let castle_rook_move = match mov.move_type {
+            MoveType::Castle => Some(self.castle_rook_move(&mov)?),
+            _ => None,
+        };
+        if let MoveType::DoublePawnPush(en_passent) = mov.move_type {
+            let skipped = mov.from.offset(0, self.current_turn.board_direction());
+            if piece_to_move.piece_type() != PieceType::Pawn
+                || mov.from.offset(0, 2 * self.current_turn.board_direction()) != Some(mov.to)
+                || skipped != Some(en_passent)
+            {
+                anyhow::bail!(
+                    "Invalid double pawn push {} with en passant field {}",
+                    mov,
+                    en_passent
+                );
+            }
+        }
 
         // If rook or king moves, remove castle rights
         // Save castle rights for unmake_move
         let cur_castle_rights = (self.white_castle_rights, self.black_castle_rights);
         let cur_en_passent = self.en_passent_field;
+        // Captures and pawn moves reset the halfmove clock
+        let resets_clock = piece_to_move.piece_type() == PieceType::Pawn
+            || matches!(
+                mov.move_type,
+                MoveType::Capture(_) | MoveType::EnPassantCapture | MoveType::PromotionCapture(..)
+            );
+
+        // The fields whose pieces change. Their keys and the state not on the board are taken
+        // out of the hash now and added back after the move, instead of hashing every field again
+        let changed_fields = [
+            Some(mov.from),
+            Some(mov.to),
+            (mov.move_type == MoveType::EnPassantCapture)
+                .then(|| Position::new_unchecked(mov.to.file(), mov.from.rank())),
+            castle_rook_move.map(|(rook_from, _)| rook_from),
+            castle_rook_move.map(|(_, rook_to)| rook_to),
+        ];
+        let mut hash = self.hash ^ self.state_hash();
+        for field in changed_fields.iter().flatten() {
+            hash ^= self.field_hash(field);
+        }
 
         // Reset en passent
         self.en_passent_field = None;
@@ -96,31 +356,12 @@ impl Game {
 
         match &mov.move_type {
             MoveType::Castle => {
-                let root_rank = self.current_turn.root_rank();
-                if mov.from.y != root_rank {
-                    panic!("Invalid castle move.");
-                }
-                match mov.to.file() {
-                    6 => {
-                        // Move king
-                        self.board.make_move(&mov.from, &mov.to);
-                        // Move rook
-                        self.board.make_move(
-                            &Position::new_unchecked(7, root_rank),
-                            &Position::new_unchecked(5, root_rank),
-                        );
-                    }
-                    2 => {
-                        // Move king
-                        self.board.make_move(&mov.from, &mov.to);
-                        // Move rook
-                        self.board.make_move(
-                            &Position::new_unchecked(0, root_rank),
-                            &Position::new_unchecked(3, root_rank),
-                        );
-                    }
-                    _ => panic!("Invalid castle move."),
-                }
+                let (rook_from, rook_to) =
+                    castle_rook_move.expect("Castle move is validated at the start");
+                // Move king
+                self.board.make_move(&mov.from, &mov.to);
+                // Move rook
+                self.board.make_move(&rook_from, &rook_to);
             }
             MoveType::PromotionCapture(promotion_type, _)
             | MoveType::PromotionQuite(promotion_type) => {
@@ -148,21 +389,97 @@ impl Game {
             cur_castle_rights.0,
             cur_castle_rights.1,
             cur_en_passent,
+            self.halfmove_clock,
+            self.hash,
         ));
+        for field in changed_fields.iter().flatten() {
+            hash ^= self.field_hash(field);
+        }
+        if resets_clock {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+        if self.current_turn == Color::Black {
+            self.fullmove_number += 1;
+        }
         self.current_turn = self.current_turn.opposite();
 
         self.bitboards = GameBitBoards::new(self);
+        // The en passant part of the state needs the new bitboards
+        self.hash = hash ^ self.state_hash();
+        Ok(())
+    }
+
+    /// Makes all the moves in order.
+    /// If one of the moves fails, all moves made by this call are unmade again,
+    /// so the game is left in the state it was before the call.
+    pub fn make_moves(&mut self, moves: impl IntoIterator<Item = Move>) -> anyhow::Result<()> {
+        for (made_moves, mov) in moves.into_iter().enumerate() {
+            if let Err(e) = self.make_move(mov) {
+                for _ in 0..made_moves {
+                    self.unmake_move();
+                }
+                return Err(e.context(format!("Failed to make move {mov}")));
+            }
+        }
         Ok(())
     }
 
+    /// Returns the from and to position of the rook for a castle move.
+    /// Fails if the king and rook are not where a castle move expects them, or the path between is blocked.
+    /// The castle rights and attacked fields are not checked.
+    pub(crate) fn castle_rook_move(&self, mov: &Move) -> anyhow::Result<(Position, Position)> {
+        let Some(color) = self
+            .board
+            .piece_at(&mov.from)
+            .filter(|piece| piece.piece_type() == PieceType::King)
+            .map(|king| king.color())
+        else {
+            anyhow::bail!("Invalid castle move {mov}. No king on {}", mov.from);
+        };
+        let root_rank = color.root_rank();
+        if mov.from != Position::new_unchecked(4, root_rank) {
+            anyhow::bail!("Invalid castle move {mov}. The king is not on its start field");
+        }
+        if mov.to.y != root_rank {
+            anyhow::bail!("Invalid castle move {mov}. The king has to stay on its rank");
+        }
+        let (rook_file, rook_to_file, empty_files) = match mov.to.file() {
+            6 => (7, 5, 5..7),
+            2 => (0, 3, 1..4),
+            _ => anyhow::bail!("Invalid castle move {mov}. Unknown king destination"),
+        };
+
+        let rook_from = Position::new_unchecked(rook_file, root_rank);
+        if self.board.piece_at(&rook_from) != Some(&Piece::new(PieceType::Rook, color)) {
+            anyhow::bail!("Invalid castle move {mov}. No rook on {rook_from}");
+        }
+        if let Some(file) = empty_files.into_iter().find(|file| {
+            self.board
+                .piece_at(&Position::new_unchecked(*file, root_rank))
+                .is_some()
+        }) {
+            anyhow::bail!(
+                "Invalid castle move {mov}. {} is occupied",
+                Position::new_unchecked(file, root_rank)
+            );
+        }
+
+        Ok((rook_from, Position::new_unchecked(rook_to_file, root_rank)))
+    }
+
     pub fn unmake_move(&mut self) {
-        let Some((mov, white_castle, black_castle, en_passent)) = self.move_stack.pop() else {
+        let Some((mov, white_castle, black_castle, en_passent, halfmove_clock, hash)) =
+            self.move_stack.pop()
+        else {
             println!("No moves to unmake.");
             return;
         };
 
         match mov.move_type {
             MoveType::PromotionCapture(_, piece_type) => {
+                // The turn is not flipped back yet. The current turn is the side that got captured
                 self.board.place_piece(
                     Piece::new(PieceType::Pawn, self.current_turn.opposite()),
                     &mov.from,
@@ -182,7 +499,7 @@ impl Game {
             MoveType::Castle => {
                 let root_rank = self.current_turn.opposite().root_rank();
                 if mov.to.y != root_rank {
-                    panic!("Invalid castle move.");
+                    unreachable!("Castle moves are validated in make_move");
                 }
                 match mov.to {
                     Position { x: 6, .. } => {
@@ -203,7 +520,7 @@ impl Game {
                             &Position::new_unchecked(0, root_rank),
                         );
                     }
-                    _ => panic!("Invalid castle move."),
+                    _ => unreachable!("Castle moves are validated in make_move"),
                 }
             }
             MoveType::Capture(piece_type) => {
@@ -230,16 +547,30 @@ impl Game {
         self.white_castle_rights = white_castle;
         self.black_castle_rights = black_castle;
         self.en_passent_field = en_passent;
+        self.halfmove_clock = halfmove_clock;
 
         self.current_turn = self.current_turn.opposite();
+        if self.current_turn == Color::Black {
+            self.fullmove_number -= 1;
+        }
 
         self.bitboards = GameBitBoards::new(self);
+        self.hash = hash;
     }
 
     pub fn board(&self) -> &Board {
         &self.board
     }
 
+    /// Edits the board and rebuilds the bitboards afterwards, so they stay in sync.
+    /// The move history is cleared, because the moves can't be unmade on the edited board.
+    pub fn edit(&mut self, f: impl FnOnce(&mut Board)) {
+        f(&mut self.board);
+        self.move_stack.clear();
+        self.bitboards = GameBitBoards::new(self);
+        self.recompute_hash();
+    }
+
     pub fn current_turn(&self) -> Color {
         self.current_turn
     }
@@ -271,6 +602,7 @@ impl Game {
         }
     }
 
+    /// The stored hash is not updated, call [Game::recompute_hash] after changing the rights.
     pub fn castle_rights_mut(&mut self, color: Color) -> &mut CastleRights {
         match color {
             Color::White => self.white_castle_rights_mut(),
@@ -278,20 +610,328 @@ impl Game {
         }
     }
 
+    /// Checks that the internal state of the game is consistent.
+    /// - The bitboards match the pieces on the board
+    /// - The stored hash matches the position
+    /// - Each side has exactly one king
+    /// - Castle rights are only set, when king and rook are on their start squares
+    #[cfg(debug_assertions)]
+    pub fn validate_invariants(&self) -> anyhow::Result<()> {
+        if self.bitboards != GameBitBoards::new(self) {
+            anyhow::bail!("Bitboards are out of sync with the board.");
+        }
+        if self.hash != self.zobrist_hash() {
+            anyhow::bail!("Stored hash is out of sync with the position.");
+        }
+
+        for color in [Color::White, Color::Black] {
+            let kings = self
+                .board
+                .iter()
+                .filter(|(_, piece)| {
+                    piece.is_some_and(|p| p.piece_type() == PieceType::King && p.color() == color)
+                })
+                .count();
+            if kings != 1 {
+                anyhow::bail!("{color:?} has {kings} kings.");
+            }
+
+            let root_rank = color.root_rank();
+            let has_piece = |x: u8, piece_type: PieceType| {
+                self.board.piece_at(&Position::new_unchecked(x, root_rank))
+                    == Some(&Piece::new(piece_type, color))
+            };
+            let rights = self.castle_rights(color);
+            if (rights.king_side() || rights.queen_side()) && !has_piece(4, PieceType::King) {
+                anyhow::bail!("{color:?} has castle rights, but the king has moved.");
+            }
+            if rights.king_side() && !has_piece(7, PieceType::Rook) {
+                anyhow::bail!("{color:?} has king side castle rights, but no rook on the h file.");
+            }
+            if rights.queen_side() && !has_piece(0, PieceType::Rook) {
+                anyhow::bail!("{color:?} has queen side castle rights, but no rook on the a file.");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks if the move can be played right now.
+    /// The move has to match a legal move exactly, including its move type.
+    pub fn is_legal(&self, mov: &Move) -> bool {
+        if self
+            .board
+            .piece_at(&mov.from)
+            .is_none_or(|piece| piece.color() != self.current_turn)
+        {
+            return false;
+        }
+        MoveGenerator::new(self)
+            .legal_moves(&mov.from)
+            .contains(mov)
+    }
+
+    /// Returns the fields the piece at `from` can legally move to, empty if there is no piece.
+    /// Works for the pieces of both colors, e.g. to show hints while the opponent thinks.
+    pub fn legal_destinations(&self, from: &Position) -> Bitboard {
+        Bitboard::from_positions(
+            MoveGenerator::new(self)
+                .legal_moves(from)
+                .into_iter()
+                .map(|mov| mov.to),
+        )
+    }
+
+    /// Returns why the move can't be played right now, or `None` if it is legal.
+    pub fn why_illegal(&self, mov: &Move) -> Option<IllegalReason> {
+        let Some(piece) = self.board.piece_at(&mov.from) else {
+            return Some(IllegalReason::NoPieceAtSource);
+        };
+        if piece.color() != self.current_turn {
+            return Some(IllegalReason::WrongColor);
+        }
+        if self.is_legal(mov) {
+            return None;
+        }
+        if mov.move_type == MoveType::Castle {
+            return Some(self.castle_illegal_reason(mov));
+        }
+        if self.resolve_move(mov).is_some() {
+            return Some(IllegalReason::WrongMoveType);
+        }
+
+        let move_generator = MoveGenerator::new(self);
+        if !move_generator
+            .pseudo_legal_moves(&mov.from)
+            .iter()
+            .any(|pseudo| pseudo.to == mov.to)
+        {
+            return Some(IllegalReason::CantMoveThere);
+        }
+        if self
+            .bitboards
+            .pinned_pieces(self.current_turn)
+            .contains(&mov.from)
+        {
+            return Some(IllegalReason::PinnedPiece);
+        }
+        Some(IllegalReason::LeavesKingInCheck)
+    }
+
+    fn castle_illegal_reason(&self, mov: &Move) -> IllegalReason {
+        let root_rank = self.current_turn.root_rank();
+        let side = match mov.to.file() {
+            6 => CastleSide::King,
+            2 => CastleSide::Queen,
+            _ => return IllegalReason::CantMoveThere,
+        };
+        if mov.from != Position::new_unchecked(4, root_rank) || mov.to.rank() != root_rank {
+            return IllegalReason::CantMoveThere;
+        }
+        let rights = self.castle_rights(self.current_turn);
+        if !rights.sides().any(|available| available == side) {
+            return IllegalReason::NoCastleRights;
+        }
+        // The king is on its field, so only the rook or the fields in between can be wrong
+        if self.castle_rook_move(mov).is_err() {
+            return IllegalReason::PathBlocked;
+        }
+        let attacks = self.bitboards.attacks(self.current_turn.opposite());
+        let (first, last) = (
+            mov.from.file().min(mov.to.file()),
+            mov.from.file().max(mov.to.file()),
+        );
+        if (first..=last).any(|file| attacks.contains(&Position::new_unchecked(file, root_rank))) {
+            return IllegalReason::ThroughCheck;
+        }
+        IllegalReason::CantMoveThere
+    }
+
+    /// Finds the legal move matching the from and to squares of the given move.
+    /// Useful for moves parsed from uci strings, which don't know about captures or castling.
+    /// If the given move has no promotion, any promotion matches.
+    pub fn resolve_move(&self, to_make: &Move) -> Option<Move> {
+        MoveGenerator::new(self)
+            .all_legal_moves(self.current_turn)
+            .into_iter()
+            .find(|m| {
+                let promotion_type = match (&to_make.move_type, &m.move_type) {
+                    (
+                        MoveType::PromotionQuite(a),
+                        MoveType::PromotionQuite(b) | MoveType::PromotionCapture(b, _),
+                    ) => a == b,
+                    _ => true,
+                };
+                promotion_type && m.to == to_make.to && m.from == to_make.from
+            })
+    }
+
+    /// Plays the moves one after another and evaluates every resulting position with the given search depth.
+    /// The scores are seen from white. Stops at the first move that can't be made.
+    pub fn analyze_line(&self, moves: &[Move], depth: u32) -> Vec<(Move, i32)> {
+        let mut game = self.clone();
+        let mut result = Vec::with_capacity(moves.len());
+        for mov in moves {
+            if game.make_move(*mov).is_err() {
+                break;
+            }
+            result.push((*mov, search::evaluate_position(&mut game, depth)));
+        }
+        result
+    }
+
+    /// Converts a move in standard algebraic notation to uci (e.g. `Nf3` to `g1f3`)
+    pub fn san_to_uci(&self, san: &str) -> anyhow::Result<String> {
+        San::parse_move(self, san).map(|mov| mov.to_uci())
+    }
+
+    /// Converts a uci move to standard algebraic notation (e.g. `g1f3` to `Nf3`)
+    pub fn uci_to_san(&self, uci: &str) -> anyhow::Result<String> {
+        let mov = uci.parse::<Move>()?;
+        let mov = self
+            .resolve_move(&mov)
+            .with_context(|| format!("Move {uci} is not legal in this position"))?;
+        San::from_move(self, &mov)
+    }
+
+    /// Returns the zobrist hash of the position, as kept up to date by the moves.
+    /// Equal for the same position, no matter if it was parsed from fen or reached by moves.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Rebuilds the stored hash from scratch, moves update it on their own.
+    /// Needed after changing the castle rights through [Game::castle_rights_mut].
+    pub fn recompute_hash(&mut self) {
+        self.hash = self.zobrist_hash();
+    }
+
+    /// Computes the zobrist hash of the position from scratch.
+    /// Includes pieces, side to move, castle rights and the en passant file.
+    /// The en passant file is only included if a pawn attacks the field, so the same position
+    /// is recognized as repetition after a double pawn push. Unlike in fen, pins are ignored.
+    pub fn zobrist_hash(&self) -> u64 {
+        let mut hash = self.state_hash();
+        for (position, piece) in self.board.iter() {
+            if let Some(piece) = piece {
+                hash ^= Zobrist::piece(piece, &position);
+            }
+        }
+        hash
+    }
+
+    // The part of the hash not on the board: side to move, castle rights and en passant
+    fn state_hash(&self) -> u64 {
+        let mut hash = 0;
+        if self.current_turn == Color::Black {
+            hash ^= Zobrist::black_to_move();
+        }
+        hash ^= Zobrist::castle_rights(Color::White, self.white_castle_rights);
+        hash ^= Zobrist::castle_rights(Color::Black, self.black_castle_rights);
+        if let Some(en_passent) = self.capturable_en_passent() {
+            hash ^= Zobrist::en_passant_file(en_passent.file());
+        }
+        hash
+    }
+
+    fn field_hash(&self, field: &Position) -> u64 {
+        self.board
+            .piece_at(field)
+            .map_or(0, |piece| Zobrist::piece(piece, field))
+    }
+
+    /// Checks if both games are in the same position, no matter how it was reached.
+    /// Unlike `==` the move history is ignored. The zobrist hash is compared first.
+    /// Like in the hash, the en passant field only counts if it can be captured.
+    pub fn same_position(&self, other: &Game) -> bool {
+        self.hash == other.hash
+            && self.board == other.board
+            && self.current_turn == other.current_turn
+            && self.white_castle_rights == other.white_castle_rights
+            && self.black_castle_rights == other.black_castle_rights
+            && self.capturable_en_passent() == other.capturable_en_passent()
+    }
+
+    /// Returns the zobrist hashes of all positions before the current one, oldest first.
+    pub fn position_hashes(&self) -> Vec<u64> {
+        self.move_stack.iter().map(|(.., hash)| *hash).collect()
+    }
+
+    // The en passant field, if a pawn of the side to move attacks it. Pins are ignored to keep it cheap
+    fn capturable_en_passent(&self) -> Option<Position> {
+        self.en_passent_field.filter(|field| {
+            (Bitboard::pawn_attacks(self.current_turn.opposite(), field)
+                & self.bitboards.pawns(self.current_turn))
+            .count()
+                > 0
+        })
+    }
+
+    /// Returns the pawns of the side to move that can legally capture en passant.
+    /// Pawns that would expose their king, even by leaving the rank together with the captured pawn, are excluded.
+    pub fn en_passant_capturers(&self) -> Vec<Position> {
+        let Some(en_passent) = self.en_passent_field else {
+            return Vec::new();
+        };
+        let direction = self.current_turn.board_direction();
+        [-1, 1]
+            .into_iter()
+            .filter_map(|dx| en_passent.offset(dx, -direction))
+            .filter(|pos| self.en_passant_available_for(pos).is_some())
+            .collect()
+    }
+
+    /// Returns the en passant target field if the pawn at `from` can legally capture en passant.
+    pub fn en_passant_available_for(&self, from: &Position) -> Option<Position> {
+        let en_passent = self.en_passent_field?;
+        if self.board.piece_at(from) != Some(&Piece::new(PieceType::Pawn, self.current_turn)) {
+            return None;
+        }
+        MoveGenerator::new(self)
+            .legal_moves(from)
+            .into_iter()
+            .any(|mov| mov.move_type == MoveType::EnPassantCapture)
+            .then_some(en_passent)
+    }
+
+    /// Returns the standard fen, see [Fen::from_game].
+    pub fn fen(&self) -> String {
+        Fen::from_game(self)
+    }
+
+    /// Returns the fen with the stored en passant field, see [Fen::from_game_raw].
+    pub fn fen_raw(&self) -> String {
+        Fen::from_game_raw(self)
+    }
+
+    /// Returns the castling field of the fen string.
+    /// White rights come before black rights, king side before queen side (e.g. `Kq`).
+    /// Returns `-` if neither side can castle.
+    pub fn castling_fen(&self) -> String {
+        let mut res = String::new();
+        for side in self.white_castle_rights.sides() {
+            res.push(side.fen_char());
+        }
+        for side in self.black_castle_rights.sides() {
+            res.push(side.fen_char().to_ascii_lowercase());
+        }
+        if res.is_empty() {
+            res.push('-');
+        }
+        res
+    }
+
     pub fn en_passent_field(&self) -> Option<Position> {
         self.en_passent_field
     }
 
     pub fn last_move(&self) -> Option<Move> {
-        self.move_stack.last().map(|(mov, _, _, _)| *mov)
+        self.move_stack.last().map(|(mov, ..)| *mov)
     }
 
     // The newest move is at the end of the stack
     pub fn move_stack(&self) -> Vec<Move> {
-        self.move_stack
-            .iter()
-            .map(|(mov, _, _, _)| *mov)
-            .collect()
+        self.move_stack.iter().map(|(mov, ..)| *mov).collect()
     }
 
     /// # Example
@@ -310,17 +950,28 @@ impl Game {
     /// ```
     #[cfg_attr(coverage_nightly, coverage(off))]
     pub fn print_custom(&self, printer: impl BoardPrinter) {
-        println!("+---+---+---+---+---+---+---+---+");
+        self.render_custom(printer, &mut io::stdout())
+            .expect("Failed to print the board");
+    }
+
+    /// Writes the board like [Game::print_custom] to any writer, e.g. a file or a buffer.
+    pub fn render_custom(
+        &self,
+        printer: impl BoardPrinter,
+        out: &mut impl Write,
+    ) -> io::Result<()> {
+        writeln!(out, "+---+---+---+---+---+---+---+---+")?;
         for i in 0..8 {
-            print!("|");
+            write!(out, "|")?;
             for j in 0..8 {
                 let pos = Position::new_unchecked(j, 7 - i);
-                print!(" {} |", printer.get_char(pos, self));
+                let (left, right) = printer.brackets(pos, self);
+                write!(out, "{left}{}{right}|", printer.get_char(pos, self))?;
             }
-            println!(" {}", 8 - i);
-            println!("+---+---+---+---+---+---+---+---+");
+            writeln!(out, " {}", 8 - i)?;
+            writeln!(out, "+---+---+---+---+---+---+---+---+")?;
         }
-        println!("  a   b   c   d   e   f   g   h  ");
+        writeln!(out, "  a   b   c   d   e   f   g   h  ")
     }
 
     #[cfg_attr(coverage_nightly, coverage(off))]
@@ -329,6 +980,120 @@ impl Game {
     }
 }
 
+/// Builds a game step by step.
+/// Starts with an empty board, white to move, no castle rights and no en passant field.
+/// # Example
+/// ```
+/// use chust::{color::Color, game::{CastleRights, GameBuilder}, piece::Piece, piece_type::PieceType, position::Position};
+/// let game = GameBuilder::default()
+///     .piece(Position::E1, Piece::new(PieceType::King, Color::White))
+///     .piece(Position::H1, Piece::new(PieceType::Rook, Color::White))
+///     .piece(Position::E8, Piece::new(PieceType::King, Color::Black))
+///     .castle_rights(Color::White, CastleRights::KingSide)
+///     .build();
+/// ```
+#[derive(Debug, Clone)]
+pub struct GameBuilder {
+    board: Board,
+    turn: Color,
+    white_castle_rights: CastleRights,
+    black_castle_rights: CastleRights,
+    en_passent_field: Option<Position>,
+}
+
+impl Default for GameBuilder {
+    fn default() -> Self {
+        Self {
+            board: Board::default(),
+            turn: Color::White,
+            white_castle_rights: CastleRights::None,
+            black_castle_rights: CastleRights::None,
+            en_passent_field: None,
+        }
+    }
+}
+
+impl GameBuilder {
+    pub fn turn(mut self, color: Color) -> Self {
+        self.turn = color;
+        self
+    }
+
+    pub fn castle_rights(mut self, color: Color, rights: CastleRights) -> Self {
+        match color {
+            Color::White => self.white_castle_rights = rights,
+            Color::Black => self.black_castle_rights = rights,
+        }
+        self
+    }
+
+    pub fn en_passant(mut self, position: Position) -> Self {
+        self.en_passent_field = Some(position);
+        self
+    }
+
+    pub fn piece(mut self, position: Position, piece: Piece) -> Self {
+        self.board.place_piece(piece, &position);
+        self
+    }
+
+    pub fn build(self) -> Game {
+        Game::new(
+            self.board,
+            self.turn,
+            self.white_castle_rights,
+            self.black_castle_rights,
+            self.en_passent_field,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameResult {
+    WhiteWins,
+    BlackWins,
+    Draw,
+    InProgress,
+}
+
+impl Display for GameResult {
+    /// Writes the result as used in pgn (e.g. `1-0`)
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let result = match self {
+            GameResult::WhiteWins => "1-0",
+            GameResult::BlackWins => "0-1",
+            GameResult::Draw => "1/2-1/2",
+            GameResult::InProgress => "*",
+        };
+        write!(f, "{result}")
+    }
+}
+
+/// Why a move can't be played, see [Game::why_illegal].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IllegalReason {
+    NoPieceAtSource,
+    WrongColor,
+    /// The piece can't reach the target field, even ignoring checks
+    CantMoveThere,
+    /// The move exists, but with another move type (e.g. a capture given as quiet move)
+    WrongMoveType,
+    LeavesKingInCheck,
+    /// The piece would leave the line between its king and the pinning piece
+    PinnedPiece,
+    /// The king castles out of, through or into check
+    ThroughCheck,
+    NoCastleRights,
+    /// The rook is missing or a piece stands between king and rook
+    PathBlocked,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DrawReason {
+    FiftyMoves,
+    ThreefoldRepetition,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum CastleRights {
     None,
@@ -337,7 +1102,49 @@ pub enum CastleRights {
     Both,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CastleSide {
+    King,
+    Queen,
+}
+
+impl CastleSide {
+    /// The file the king ends on after castling
+    pub fn king_file(&self) -> u8 {
+        match self {
+            CastleSide::King => 6,
+            CastleSide::Queen => 2,
+        }
+    }
+
+    /// The file the rook starts on
+    pub fn rook_file(&self) -> u8 {
+        match self {
+            CastleSide::King => 7,
+            CastleSide::Queen => 0,
+        }
+    }
+
+    /// Uppercase character used in the castling field of the fen
+    pub fn fen_char(&self) -> char {
+        match self {
+            CastleSide::King => 'K',
+            CastleSide::Queen => 'Q',
+        }
+    }
+}
+
 impl CastleRights {
+    /// Iterates over the available sides, king side first.
+    pub fn sides(&self) -> impl Iterator<Item = CastleSide> {
+        [
+            self.king_side().then_some(CastleSide::King),
+            self.queen_side().then_some(CastleSide::Queen),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
     pub fn remove_king_side(&mut self) {
         match self {
             CastleRights::KingSide => *self = CastleRights::None,
@@ -392,12 +1199,356 @@ impl BitOrAssign for CastleRights {
 
 #[cfg(test)]
 mod tests {
-    use std::str::FromStr;
-
     use crate::moves::PromotionType;
 
     use super::*;
 
+    #[test]
+    fn parse_from_str() {
+        let game = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+            .parse::<Game>()
+            .unwrap();
+        assert_eq!(game, Game::default());
+
+        assert!("not a fen".parse::<Game>().is_err());
+    }
+
+    #[test]
+    fn san_uci_conversion() {
+        let game = Game::default();
+        assert_eq!(game.san_to_uci("Nf3").unwrap(), "g1f3");
+        assert_eq!(game.uci_to_san("g1f3").unwrap(), "Nf3");
+        assert_eq!(game.uci_to_san("e2e4").unwrap(), "e4");
+
+        assert!(game.san_to_uci("Nf6").is_err());
+        assert!(game.uci_to_san("g1g3").is_err());
+    }
+
+    #[test]
+    fn king_attack_rays() {
+        let game = Fen::parse_game("4k3/8/8/8/4r3/8/8/4K3 w - - 0 1").unwrap();
+        let rays = game.king_attack_rays(Color::White);
+        assert_eq!(rays.len(), 1);
+        let expected = [Position::E2, Position::E3, Position::E4]
+            .into_iter()
+            .fold(Bitboard::default(), |acc, p| acc | Bitboard::from(p));
+        assert_eq!(rays[0], expected);
+        assert!(game.king_attack_rays(Color::Black).is_empty());
+
+        // Double check by knight and rook
+        let game = Fen::parse_game("4k3/8/8/8/4r3/3n4/8/4K3 w - - 0 1").unwrap();
+        let rays = game.king_attack_rays(Color::White);
+        assert_eq!(rays.len(), 2);
+        assert!(rays.contains(&Bitboard::from(Position::D3)));
+    }
+
+    #[test]
+    fn en_passant_available_for() {
+        let game = Game::from_str("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        assert_eq!(
+            game.en_passant_available_for(&Position::E5),
+            Some(Position::D6)
+        );
+        // Not a pawn next to the en passant field
+        assert_eq!(game.en_passant_available_for(&Position::E1), None);
+
+        let game = Game::from_str("4k3/8/8/3pP3/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(game.en_passant_available_for(&Position::E5), None);
+    }
+
+    #[test]
+    fn zobrist_hash_en_passant() {
+        // No black pawn next to e4, so the en passant square doesn't matter
+        let mut game = Game::default();
+        game.make_move(Move::new(
+            Position::E2,
+            Position::E4,
+            MoveType::DoublePawnPush(Position::E3),
+        ))
+        .unwrap();
+        let without_en_passant =
+            Fen::parse_game("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        assert_eq!(game.en_passent_field(), Some(Position::E3));
+        assert_eq!(game.zobrist_hash(), without_en_passant.zobrist_hash());
+
+        // Black pawn on d4 can capture en passant
+        let capturable = Fen::parse_game("4k3/8/8/8/3pP3/8/8/4K3 b - e3 0 1").unwrap();
+        let not_capturable = Fen::parse_game("4k3/8/8/8/3pP3/8/8/4K3 b - - 0 1").unwrap();
+        assert_ne!(capturable.zobrist_hash(), not_capturable.zobrist_hash());
+    }
+
+    #[test]
+    fn zobrist_hash_differs() {
+        let game = Game::default();
+        assert_eq!(game.zobrist_hash(), Game::default().zobrist_hash());
+
+        let black_to_move =
+            Fen::parse_game("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq - 0 1").unwrap();
+        assert_ne!(game.zobrist_hash(), black_to_move.zobrist_hash());
+
+        let no_castle =
+            Fen::parse_game("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w Kkq - 0 1").unwrap();
+        assert_ne!(game.zobrist_hash(), no_castle.zobrist_hash());
+
+        // Knight out and back results in the same hash
+        let mut moved = Game::default();
+        moved
+            .make_moves([
+                Move::new(Position::G1, Position::F3, MoveType::Quiet),
+                Move::new(Position::G8, Position::F6, MoveType::Quiet),
+                Move::new(Position::F3, Position::G1, MoveType::Quiet),
+                Move::new(Position::F6, Position::G8, MoveType::Quiet),
+            ])
+            .unwrap();
+        assert_eq!(game.zobrist_hash(), moved.zobrist_hash());
+    }
+
+    fn assert_incremental_hash(game: &mut Game, depth: u32) {
+        assert_eq!(game.hash(), game.zobrist_hash(), "{game}");
+        if depth == 0 {
+            return;
+        }
+        for mov in MoveGenerator::new(game).all_legal_moves(game.current_turn()) {
+            game.make_move(mov).unwrap();
+            assert_incremental_hash(game, depth - 1);
+            game.unmake_move();
+        }
+    }
+
+    #[test]
+    fn incremental_hash_matches_recomputed() {
+        for fen in [
+            // Castling, en passant and promotions
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+        ] {
+            let mut game = Fen::parse_game(fen).unwrap();
+            assert_incremental_hash(&mut game, 2);
+        }
+    }
+
+    #[test]
+    fn hash_matches_parsed_fen() {
+        let mut game = Game::default();
+        game.make_moves([
+            Move::new(
+                Position::E2,
+                Position::E4,
+                MoveType::DoublePawnPush(Position::E3),
+            ),
+            Move::new(
+                Position::E7,
+                Position::E5,
+                MoveType::DoublePawnPush(Position::E6),
+            ),
+            Move::new(Position::G1, Position::F3, MoveType::Quiet),
+            Move::new(Position::B8, Position::C6, MoveType::Quiet),
+            Move::new(Position::F1, Position::C4, MoveType::Quiet),
+            Move::new(Position::G8, Position::F6, MoveType::Quiet),
+            Move::new(Position::E1, Position::G1, MoveType::Castle),
+        ])
+        .unwrap();
+        let parsed =
+            Fen::parse_game("r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQ1RK1 b kq - 5 4")
+                .unwrap();
+        assert_eq!(parsed.hash(), game.hash());
+        assert_eq!(game.hash(), game.zobrist_hash());
+
+        // The stored hash follows unmaking moves
+        game.unmake_move();
+        assert_eq!(game.hash(), game.zobrist_hash());
+        assert_ne!(game.hash(), parsed.hash());
+
+        // Changed castle rights need a recompute
+        let mut edited = Game::default();
+        edited.castle_rights_mut(Color::White).remove_both();
+        edited.recompute_hash();
+        let no_white_castle =
+            Fen::parse_game("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w kq - 0 1").unwrap();
+        assert_eq!(edited.hash(), no_white_castle.hash());
+    }
+
+    #[test]
+    fn game_builder() {
+        let built = GameBuilder::default()
+            .turn(Color::Black)
+            .piece(Position::E1, Piece::new(PieceType::King, Color::White))
+            .piece(Position::E4, Piece::new(PieceType::Pawn, Color::White))
+            .piece(Position::D4, Piece::new(PieceType::Pawn, Color::Black))
+            .piece(Position::E8, Piece::new(PieceType::King, Color::Black))
+            .castle_rights(Color::Black, CastleRights::QueenSide)
+            .en_passant(Position::E3)
+            .build();
+
+        let mut board = Board::default();
+        board.place_piece(Piece::new(PieceType::King, Color::White), &Position::E1);
+        board.place_piece(Piece::new(PieceType::Pawn, Color::White), &Position::E4);
+        board.place_piece(Piece::new(PieceType::Pawn, Color::Black), &Position::D4);
+        board.place_piece(Piece::new(PieceType::King, Color::Black), &Position::E8);
+        let expected = Game::new(
+            board,
+            Color::Black,
+            CastleRights::None,
+            CastleRights::QueenSide,
+            Some(Position::E3),
+        );
+
+        assert_eq!(built, expected);
+    }
+
+    #[test]
+    fn edit_board() {
+        let mut game = Game::default();
+        game.make_move(Move::new(Position::G1, Position::F3, MoveType::Quiet))
+            .unwrap();
+        game.edit(|board| {
+            board.remove_piece(&Position::D7);
+            board.place_piece(Piece::new(PieceType::Queen, Color::White), &Position::A4);
+        });
+        assert_valid(&game);
+        assert!(game.move_stack().is_empty());
+        assert!(game
+            .bitboards()
+            .queens(Color::White)
+            .contains(&Position::A4));
+        assert!(!game.bitboards().pawns(Color::Black).contains(&Position::D7));
+
+        // The new queen gives check, black has to block it with c6, b5, Nc6, Nd7, Bd7 or Qd7
+        assert!(game.is_in_check());
+        let legal_moves = MoveGenerator::new(&game).all_legal_moves(Color::Black);
+        assert_eq!(legal_moves.len(), 6);
+    }
+
+    #[test]
+    fn reject_capturing_own_piece() {
+        let mut game = Game::default();
+        let before = game.clone();
+        let self_capture = Move::new(
+            Position::D1,
+            Position::D2,
+            MoveType::Capture(PieceType::Pawn),
+        );
+        assert!(game.make_move(self_capture).is_err());
+        assert_eq!(game, before);
+    }
+
+    #[test]
+    fn analyze_line() {
+        let game = Game::default();
+        let mut current = game.clone();
+        let mut moves = Vec::new();
+        for san in ["e4", "e5", "Qh5", "Nc6", "Bc4", "Nf6", "Qxf7#"] {
+            let mov = San::parse_move(&current, san).unwrap();
+            current.make_move(mov).unwrap();
+            moves.push(mov);
+        }
+
+        let analysis = game.analyze_line(&moves, 2);
+        assert_eq!(analysis.len(), moves.len());
+        assert!(analysis.iter().zip(&moves).all(|((a, _), b)| a == b));
+        // The opening is about equal
+        assert!(analysis[..5].iter().all(|(_, score)| score.abs() < 200));
+        // Nf6 is the blunder allowing mate, seen from white
+        assert_eq!(search::score_to_mate_distance(analysis[5].1), Some(1));
+        assert_eq!(analysis[6].1, search::MATE_SCORE);
+    }
+
+    #[test]
+    fn game_result() {
+        assert_eq!(Game::default().result(), GameResult::InProgress);
+        let mate = Game::from_str("R5k1/5ppp/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(mate.result(), GameResult::WhiteWins);
+        let stalemate = Game::from_str("7k/5Q2/8/8/8/8/8/4K3 b - - 0 1").unwrap();
+        assert_eq!(stalemate.result(), GameResult::Draw);
+        assert_eq!(GameResult::Draw.to_string(), "1/2-1/2");
+    }
+
+    #[test]
+    fn reject_malformed_castle() {
+        let mut game = Game::from_str("r3k2r/8/8/8/8/8/8/RN2K1NR w KQkq - 0 1").unwrap();
+        let before = game.clone();
+        for (from, to) in [
+            // Pieces in between
+            (Position::E1, Position::G1),
+            (Position::E1, Position::C1),
+            // Not a castle destination
+            (Position::E1, Position::F1),
+            (Position::E1, Position::E3),
+            // No king on the start field
+            (Position::A1, Position::C1),
+        ] {
+            assert!(game
+                .make_move(Move::new(from, to, MoveType::Castle))
+                .is_err());
+            assert_eq!(game, before);
+        }
+
+        // Rook is missing
+        let mut game = Game::from_str("4k3/8/8/8/8/8/8/4K3 w KQ - 0 1").unwrap();
+        assert!(game
+            .make_move(Move::new(Position::E1, Position::G1, MoveType::Castle))
+            .is_err());
+    }
+
+    #[test]
+    fn material_count() {
+        let game = Game::default();
+        for color in [Color::White, Color::Black] {
+            let material = game.material(color);
+            assert_eq!(material[PieceType::Pawn as usize], 8);
+            assert_eq!(material[PieceType::Knight as usize], 2);
+            assert_eq!(material[PieceType::Bishop as usize], 2);
+            assert_eq!(material[PieceType::Rook as usize], 2);
+            assert_eq!(material[PieceType::Queen as usize], 1);
+            assert_eq!(material[PieceType::King as usize], 1);
+        }
+
+        let game = Game::from_str("4k3/8/8/3p4/8/8/1Q3PP1/2R1K3 w - - 0 1").unwrap();
+        assert_eq!(game.material(Color::White), [2, 0, 0, 1, 1, 1]);
+        assert_eq!(game.material(Color::Black), [1, 0, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn castle_rights_sides() {
+        let sides = |rights: CastleRights| rights.sides().collect::<Vec<_>>();
+        assert_eq!(
+            sides(CastleRights::Both),
+            vec![CastleSide::King, CastleSide::Queen]
+        );
+        assert_eq!(sides(CastleRights::KingSide), vec![CastleSide::King]);
+        assert_eq!(sides(CastleRights::QueenSide), vec![CastleSide::Queen]);
+        assert!(sides(CastleRights::None).is_empty());
+    }
+
+    #[test]
+    fn position_hashes() {
+        let mut game = Game::default();
+        assert!(game.position_hashes().is_empty());
+        game.make_moves([
+            Move::new(Position::G1, Position::F3, MoveType::Quiet),
+            Move::new(Position::G8, Position::F6, MoveType::Quiet),
+        ])
+        .unwrap();
+        let hashes = game.position_hashes();
+        assert_eq!(hashes.len(), 2);
+        assert_eq!(hashes[0], Game::default().zobrist_hash());
+        game.unmake_move();
+        assert_eq!(hashes[1], game.zobrist_hash());
+        assert_eq!(game.hash(), game.zobrist_hash());
+    }
+
+    #[test]
+    fn display_as_fen() {
+        assert_eq!(
+            format!("{}", Game::default()),
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        );
+
+        let fen = "r3k2r/8/8/8/8/8/8/R3K2R b Kq - 0 1";
+        assert_eq!(fen.parse::<Game>().unwrap().to_string(), fen);
+    }
+
     #[test]
     fn make_moves() {
         let mut game = Game::default();
@@ -449,6 +1600,78 @@ mod tests {
         );
     }
 
+    #[test]
+    fn make_multiple_moves() {
+        let mut game = Game::default();
+        game.make_moves([
+            Move::new(
+                Position::E2,
+                Position::E4,
+                MoveType::DoublePawnPush(Position::E3),
+            ),
+            Move::new(
+                Position::E7,
+                Position::E5,
+                MoveType::DoublePawnPush(Position::E6),
+            ),
+            Move::new(Position::G1, Position::F3, MoveType::Quiet),
+        ])
+        .unwrap();
+        assert_valid(&game);
+        assert_eq!(game.current_turn(), Color::Black);
+        assert_eq!(game.move_stack().len(), 3);
+        assert_eq!(
+            game.board().piece_at(&Position::F3),
+            Some(&Piece::new(PieceType::Knight, Color::White))
+        );
+
+        // Fails on the second move (white moves twice). The first move is rolled back.
+        let before = game.clone();
+        let result = game.make_moves([
+            Move::new(Position::B8, Position::C6, MoveType::Quiet),
+            Move::new(Position::B1, Position::C3, MoveType::Quiet),
+            Move::new(Position::F1, Position::C4, MoveType::Quiet),
+        ]);
+        assert!(result.is_err());
+        assert_eq!(game, before);
+    }
+
+    /// Validates the game state in debug builds
+    fn assert_valid(_game: &Game) {
+        #[cfg(debug_assertions)]
+        _game.validate_invariants().unwrap();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn validate_invariants() {
+        let mut game = Game::default();
+        game.validate_invariants().unwrap();
+
+        game.make_move(Move::new(
+            Position::E2,
+            Position::E4,
+            MoveType::DoublePawnPush(Position::E3),
+        ))
+        .unwrap();
+        game.validate_invariants().unwrap();
+
+        // Board changed without updating the bitboards
+        let mut corrupted = game.clone();
+        corrupted
+            .board
+            .place_piece(Piece::new(PieceType::Queen, Color::White), &Position::D4);
+        assert!(corrupted.validate_invariants().is_err());
+
+        // Second white king
+        let corrupted = Fen::parse_game("4k3/8/8/8/8/8/8/3KK3 w - - 0 1").unwrap();
+        assert!(corrupted.validate_invariants().is_err());
+
+        // Castle rights without the rook
+        let corrupted = Fen::parse_game("4k3/8/8/8/8/8/8/4K3 w K - 0 1").unwrap();
+        assert!(corrupted.validate_invariants().is_err());
+    }
+
     #[test]
     fn make_castles() {
         let mut game = Fen::parse_game("8/8/8/8/8/8/8/R3K2R w - - 0 1").unwrap();
@@ -600,6 +1823,36 @@ mod tests {
         );
     }
 
+    #[test]
+    fn unmake_black_promotion_capture() {
+        let mut game = Fen::parse_game("4k3/8/8/8/8/8/4p3/3RK3 b - - 0 1").unwrap();
+        let before = game.clone();
+
+        game.make_move(Move::new(
+            Position::E2,
+            Position::D1,
+            MoveType::PromotionCapture(PromotionType::Queen, PieceType::Rook),
+        ))
+        .unwrap();
+        assert_eq!(
+            game.board().piece_at(&Position::D1),
+            Some(&Piece::new(PieceType::Queen, Color::Black))
+        );
+
+        game.unmake_move();
+        assert_eq!(
+            game.board().piece_at(&Position::D1),
+            Some(&Piece::new(PieceType::Rook, Color::White))
+        );
+        assert_eq!(
+            game.board().piece_at(&Position::E2),
+            Some(&Piece::new(PieceType::Pawn, Color::Black))
+        );
+        assert_eq!(game.current_turn(), Color::Black);
+        assert_eq!(game, before);
+        assert_valid(&game);
+    }
+
     #[test]
     fn en_passent_capture() {
         let mut game = Fen::parse_game("8/8/8/2Pp4/8/8/8/8 w - d6 0 1").unwrap();
@@ -663,6 +1916,27 @@ mod tests {
         assert_eq!(rights, CastleRights::None);
     }
 
+    #[test]
+    fn castling_fen_partial_rights() {
+        let all_rights = [
+            CastleRights::None,
+            CastleRights::KingSide,
+            CastleRights::QueenSide,
+            CastleRights::Both,
+        ];
+        let expected_white = ["", "K", "Q", "KQ"];
+        let expected_black = ["", "k", "q", "kq"];
+
+        for (white, expected_white) in all_rights.iter().zip(expected_white) {
+            for (black, expected_black) in all_rights.iter().zip(expected_black) {
+                let game = Game::new(Board::default(), Color::White, *white, *black, None);
+                let expected = format!("{expected_white}{expected_black}");
+                let expected = if expected.is_empty() { "-" } else { &expected };
+                assert_eq!(game.castling_fen(), expected);
+            }
+        }
+    }
+
     #[test]
     fn castle_rights_change_in_game() {
         {
@@ -688,6 +1962,7 @@ mod tests {
             game.make_move(Move::new(Position::E1, Position::D1, MoveType::Quiet))
                 .unwrap();
             assert_eq!(game.white_castle_rights(), CastleRights::None);
+            assert_valid(&game);
         }
         {
             // Black
@@ -712,6 +1987,459 @@ mod tests {
             game.make_move(Move::new(Position::E8, Position::D8, MoveType::Quiet))
                 .unwrap();
             assert_eq!(game.black_castle_rights(), CastleRights::None);
+            assert_valid(&game);
+        }
+    }
+
+    // Plays quiet non pawn moves that never reach a position twice
+    fn play_without_repetition(game: &mut Game, half_moves: usize) {
+        let mut seen = vec![game.zobrist_hash()];
+        assert!(
+            extend_without_repetition(game, &mut seen, half_moves),
+            "No unseen position left"
+        );
+    }
+
+    // Depth first search for a line of quiet piece moves that never reaches a position twice
+    fn extend_without_repetition(game: &mut Game, seen: &mut Vec<u64>, half_moves: usize) -> bool {
+        if half_moves == 0 {
+            return true;
+        }
+        let moves = MoveGenerator::new(game)
+            .all_legal_moves(game.current_turn())
+            .into_iter()
+            .filter(|mov| mov.move_type == MoveType::Quiet)
+            .filter(|mov| game.board().piece_at(&mov.from).unwrap().piece_type() != PieceType::Pawn)
+            .collect::<Vec<_>>();
+        for mov in moves {
+            game.make_move(mov).unwrap();
+            let hash = game.zobrist_hash();
+            if !seen.contains(&hash) {
+                seen.push(hash);
+                if extend_without_repetition(game, seen, half_moves - 1) {
+                    return true;
+                }
+                seen.pop();
+            }
+            game.unmake_move();
+        }
+        false
+    }
+
+    #[test]
+    fn fifty_and_seventy_five_move_rule() {
+        let mut game = Game::from_str("1n2k3/8/8/p1p1p1p1/P1P1P1P1/8/8/1N2K3 w - - 0 1").unwrap();
+        play_without_repetition(&mut game, 99);
+        assert_eq!(game.halfmove_clock(), 99);
+        assert_eq!(game.can_claim_draw(), None);
+
+        play_without_repetition(&mut game, 1);
+        assert_eq!(game.can_claim_draw(), Some(DrawReason::FiftyMoves));
+        assert!(!game.is_draw());
+        assert_eq!(game.result(), GameResult::InProgress);
+
+        play_without_repetition(&mut game, 49);
+        assert!(!game.is_draw());
+        play_without_repetition(&mut game, 1);
+        assert!(game.is_draw());
+        assert_eq!(game.result(), GameResult::Draw);
+    }
+
+    #[test]
+    fn halfmove_clock_resets() {
+        let mut game = Game::default();
+        game.make_moves(["g1f3", "g8f6", "f3g1", "e7e5"].map(|m| Move::from_str(m).unwrap()))
+            .unwrap();
+        assert_eq!(game.halfmove_clock(), 0);
+        game.make_moves(["g1f3", "b8c6"].map(|m| Move::from_str(m).unwrap()))
+            .unwrap();
+        assert_eq!(game.halfmove_clock(), 2);
+    }
+
+    #[test]
+    fn threefold_and_fivefold_repetition() {
+        let mut game = Game::default();
+        let shuffle = [
+            Move::new(Position::G1, Position::F3, MoveType::Quiet),
+            Move::new(Position::G8, Position::F6, MoveType::Quiet),
+            Move::new(Position::F3, Position::G1, MoveType::Quiet),
+            Move::new(Position::F6, Position::G8, MoveType::Quiet),
+        ];
+        game.make_moves(shuffle).unwrap();
+        assert_eq!(game.can_claim_draw(), None);
+
+        game.make_moves(shuffle).unwrap();
+        assert_eq!(game.can_claim_draw(), Some(DrawReason::ThreefoldRepetition));
+        assert!(!game.is_draw());
+
+        game.make_moves(shuffle).unwrap();
+        assert!(!game.is_draw());
+        game.make_moves(shuffle).unwrap();
+        assert!(game.is_draw());
+        assert_eq!(game.result(), GameResult::Draw);
+    }
+
+    #[test]
+    fn same_position_ignores_move_order() {
+        let mut knights_first = Game::default();
+        knights_first
+            .make_moves([
+                Move::new(Position::G1, Position::F3, MoveType::Quiet),
+                Move::new(Position::G8, Position::F6, MoveType::Quiet),
+                Move::new(Position::B1, Position::C3, MoveType::Quiet),
+            ])
+            .unwrap();
+        let mut other_order = Game::default();
+        other_order
+            .make_moves([
+                Move::new(Position::B1, Position::C3, MoveType::Quiet),
+                Move::new(Position::G8, Position::F6, MoveType::Quiet),
+                Move::new(Position::G1, Position::F3, MoveType::Quiet),
+            ])
+            .unwrap();
+        assert!(knights_first.same_position(&other_order));
+        assert_ne!(knights_first, other_order);
+
+        assert!(!knights_first.same_position(&Game::default()));
+
+        // Nobody can capture the double pushed pawn, so the en passant field doesn't count
+        let mut pawn_last = Game::default();
+        pawn_last
+            .make_moves([
+                Move::new(Position::G1, Position::F3, MoveType::Quiet),
+                Move::new(Position::G8, Position::F6, MoveType::Quiet),
+                Move::new(
+                    Position::E2,
+                    Position::E4,
+                    MoveType::DoublePawnPush(Position::E3),
+                ),
+            ])
+            .unwrap();
+        let mut pawn_first = Game::default();
+        pawn_first
+            .make_moves([
+                Move::new(
+                    Position::E2,
+                    Position::E4,
+                    MoveType::DoublePawnPush(Position::E3),
+                ),
+                Move::new(Position::G8, Position::F6, MoveType::Quiet),
+                Move::new(Position::G1, Position::F3, MoveType::Quiet),
+            ])
+            .unwrap();
+        assert_eq!(pawn_last.en_passent_field(), Some(Position::E3));
+        assert!(pawn_last.same_position(&pawn_first));
+        assert_ne!(pawn_last, pawn_first);
+    }
+
+    #[test]
+    fn status_line() {
+        let mut game = Game::default();
+        assert_eq!(
+            game.status_line(),
+            "White to move, castling KQkq, en passant -, halfmove clock 0, move 1"
+        );
+
+        game.make_moves([
+            Move::new(
+                Position::E2,
+                Position::E4,
+                MoveType::DoublePawnPush(Position::E3),
+            ),
+            Move::new(Position::G8, Position::F6, MoveType::Quiet),
+            Move::new(Position::E1, Position::E2, MoveType::Quiet),
+        ])
+        .unwrap();
+        assert_eq!(
+            game.status_line(),
+            "Black to move, castling kq, en passant -, halfmove clock 2, move 2"
+        );
+
+        let game = Game::from_str("4k3/8/8/8/4Pp2/8/8/4K3 b - e3 0 1").unwrap();
+        assert_eq!(game.fullmove_number(), 1);
+        assert_eq!(
+            game.status_line(),
+            "Black to move, castling -, en passant e3, halfmove clock 0, move 1"
+        );
+        let mut game = game;
+        game.make_move(Move::new(Position::E8, Position::D8, MoveType::Quiet))
+            .unwrap();
+        assert_eq!(game.fullmove_number(), 2);
+    }
+
+    #[test]
+    fn reject_wrong_double_push_target() {
+        let mut game = Game::default();
+        let wrong_target = Move::new(
+            Position::E2,
+            Position::E4,
+            MoveType::DoublePawnPush(Position::D3),
+        );
+        assert!(game.make_move(wrong_target).is_err());
+        assert_eq!(game, Game::default());
+
+        let not_two_fields = Move::new(
+            Position::E2,
+            Position::E3,
+            MoveType::DoublePawnPush(Position::E3),
+        );
+        assert!(game.make_move(not_two_fields).is_err());
+
+        let knight = Move::new(
+            Position::G1,
+            Position::G3,
+            MoveType::DoublePawnPush(Position::G2),
+        );
+        assert!(game.make_move(knight).is_err());
+
+        game.make_move(Move::new(
+            Position::E2,
+            Position::E4,
+            MoveType::DoublePawnPush(Position::E3),
+        ))
+        .unwrap();
+        assert_eq!(game.en_passent_field(), Some(Position::E3));
+    }
+
+    #[test]
+    fn is_legal() {
+        let game = Game::default();
+        assert!(game.is_legal(&Move::new(Position::G1, Position::F3, MoveType::Quiet)));
+        assert!(game.is_legal(&Move::new(
+            Position::E2,
+            Position::E4,
+            MoveType::DoublePawnPush(Position::E3)
+        )));
+        // Not black's turn
+        assert!(!game.is_legal(&Move::new(Position::G8, Position::F6, MoveType::Quiet)));
+        // No piece to move
+        assert!(!game.is_legal(&Move::new(Position::E4, Position::E5, MoveType::Quiet)));
+
+        // The knight is pinned to the king
+        let game = Game::from_str("4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1").unwrap();
+        assert!(!game.is_legal(&Move::new(Position::E2, Position::C3, MoveType::Quiet)));
+        assert!(game.is_legal(&Move::new(Position::E1, Position::D1, MoveType::Quiet)));
+    }
+
+    #[test]
+    fn en_passant_capturers() {
+        // Black to move, the en passant field is of no use
+        let game = Game::from_str("8/8/3p4/K1pP3r/4Rp1k/8/4P1P1/8 b - c6 0 1").unwrap();
+        assert!(game.en_passant_capturers().is_empty());
+
+        // Taking en passant would leave the king open to the rook
+        let game = Game::from_str("8/8/3p4/K1pP3r/4Rp1k/8/4P1P1/8 w - c6 0 1").unwrap();
+        assert!(game.en_passant_capturers().is_empty());
+
+        let game = Game::from_str("4k3/8/8/2PpP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        assert_eq!(
+            game.en_passant_capturers(),
+            vec![Position::C5, Position::E5]
+        );
+    }
+
+    #[test]
+    fn why_illegal() {
+        let game = Game::default();
+        let quiet = |from, to| Move::new(from, to, MoveType::Quiet);
+        assert_eq!(game.why_illegal(&quiet(Position::G1, Position::F3)), None);
+        assert_eq!(
+            game.why_illegal(&quiet(Position::E4, Position::E5)),
+            Some(IllegalReason::NoPieceAtSource)
+        );
+        assert_eq!(
+            game.why_illegal(&quiet(Position::G8, Position::F6)),
+            Some(IllegalReason::WrongColor)
+        );
+        assert_eq!(
+            game.why_illegal(&quiet(Position::G1, Position::G3)),
+            Some(IllegalReason::CantMoveThere)
+        );
+        assert_eq!(
+            game.why_illegal(&quiet(Position::E2, Position::E4)),
+            Some(IllegalReason::WrongMoveType)
+        );
+
+        // The knight is pinned, the king can't step next to the rook
+        let game = Game::from_str("4k3/4r3/8/8/8/8/4N3/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            game.why_illegal(&quiet(Position::E2, Position::C3)),
+            Some(IllegalReason::PinnedPiece)
+        );
+        let game = Game::from_str("4k3/3r4/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            game.why_illegal(&quiet(Position::E1, Position::D1)),
+            Some(IllegalReason::LeavesKingInCheck)
+        );
+
+        let castle = |to| Move::new(Position::E1, to, MoveType::Castle);
+        let game = Game::from_str("4k3/8/8/8/8/8/8/RN2K2R w K - 0 1").unwrap();
+        assert_eq!(game.why_illegal(&castle(Position::G1)), None);
+        assert_eq!(
+            game.why_illegal(&castle(Position::C1)),
+            Some(IllegalReason::NoCastleRights)
+        );
+        let game = Game::from_str("4k3/8/8/8/8/8/8/RN2K2R w KQ - 0 1").unwrap();
+        assert_eq!(
+            game.why_illegal(&castle(Position::C1)),
+            Some(IllegalReason::PathBlocked)
+        );
+        let game = Game::from_str("4k3/8/8/8/8/8/5r2/R3K2R w KQ - 0 1").unwrap();
+        assert_eq!(
+            game.why_illegal(&castle(Position::G1)),
+            Some(IllegalReason::ThroughCheck)
+        );
+    }
+
+    #[test]
+    fn legal_destinations_of_a_knight() {
+        let game = Game::from_str("4k3/8/8/8/3N4/8/8/4K3 w - - 0 1").unwrap();
+        assert_eq!(
+            game.legal_destinations(&Position::D4),
+            Bitboard::from_positions([
+                Position::B3,
+                Position::B5,
+                Position::C2,
+                Position::C6,
+                Position::E2,
+                Position::E6,
+                Position::F3,
+                Position::F5,
+            ])
+        );
+        assert_eq!(game.legal_destinations(&Position::D5), Bitboard::default());
+    }
+
+    #[test]
+    fn likely_draw_material() {
+        for fen in [
+            "4k3/8/8/3r4/8/8/3R4/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1",
+            "4k3/8/8/3q4/8/8/3Q4/4K3 w - - 0 1",
+            "4k3/5b2/8/8/8/8/8/1N2K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/4K3 w - - 0 1",
+        ] {
+            let game = Game::from_str(fen).unwrap();
+            assert!(game.is_likely_draw_material(), "{fen}");
+        }
+        for fen in [
+            "4k3/8/8/8/8/8/3Q4/4K3 w - - 0 1",
+            "4k3/8/8/3r4/8/8/3Q4/4K3 w - - 0 1",
+            "4k3/8/8/3r4/8/8/3RP3/4K3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/1NB1K3 w - - 0 1",
+            "4k3/4n3/8/8/8/8/8/1N2KN2 w - - 0 1",
+        ] {
+            let game = Game::from_str(fen).unwrap();
+            assert!(!game.is_likely_draw_material(), "{fen}");
+        }
+    }
+
+    #[test]
+    fn check_count() {
+        assert_eq!(Game::default().check_count(), 0);
+        for (fen, checks) in [
+            // Pinned rook, no check
+            ("4k3/4r3/8/8/8/8/4R3/4K3 w - - 0 1", 0),
+            ("4k3/8/8/8/8/8/3p4/4K3 w - - 0 1", 1),
+            ("4k3/8/3N4/8/8/8/8/5K2 b - - 0 1", 1),
+            ("4k3/8/8/8/8/8/8/4RK2 b - - 0 1", 1),
+            // Knight and rook
+            ("4k3/8/3N4/8/8/8/8/4RK2 b - - 0 1", 2),
+        ] {
+            let game = Game::from_str(fen).unwrap();
+            assert_eq!(game.check_count(), checks, "{fen}");
+            assert_eq!(game.is_in_check(), checks > 0, "{fen}");
         }
     }
+
+    #[test]
+    fn render_to_buffer() {
+        let game = Game::from_str("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+        let mut out = Vec::new();
+        game.render_custom(DefaultBoardPrinter, &mut out).unwrap();
+        let rendered = String::from_utf8(out).unwrap();
+        let lines = rendered.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 18);
+        assert_eq!(lines[0], "+---+---+---+---+---+---+---+---+");
+        assert_eq!(lines[1], "|   |   |   |   | k |   |   |   | 8");
+        assert_eq!(lines[15], "| R |   |   |   | K |   |   |   | 1");
+        assert_eq!(lines[17], "  a   b   c   d   e   f   g   h  ");
+        assert!(rendered.ends_with('\n'));
+    }
+
+    #[test]
+    fn pawn_attack_squares() {
+        let game = Game::from_str("k7/8/3p4/8/4P3/8/8/K7 w - - 0 1").unwrap();
+        let white = game.attack_squares(Color::White);
+        assert!(!white.contains(&Position::E5));
+        assert!(white.contains(&Position::D5));
+        assert!(white.contains(&Position::F5));
+
+        let black = game.attack_squares(Color::Black);
+        assert!(!black.contains(&Position::D5));
+        assert!(black.contains(&Position::C5));
+        assert!(black.contains(&Position::E5));
+    }
+
+    #[test]
+    fn piece_positions() {
+        let game = Game::default();
+        assert_eq!(
+            game.piece_positions(PieceType::Knight, Color::White),
+            vec![Position::B1, Position::G1]
+        );
+        assert_eq!(
+            game.piece_positions(PieceType::King, Color::Black),
+            vec![Position::E8]
+        );
+        assert_eq!(game.piece_positions(PieceType::Pawn, Color::Black).len(), 8);
+
+        let game = Game::from_str("4k3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(game
+            .piece_positions(PieceType::Queen, Color::White)
+            .is_empty());
+    }
+
+    #[test]
+    fn from_placement_map() {
+        let mut pieces = HashMap::from([
+            (Position::E1, Piece::new(PieceType::King, Color::White)),
+            (Position::E2, Piece::new(PieceType::Queen, Color::White)),
+            (Position::E8, Piece::new(PieceType::King, Color::Black)),
+        ]);
+        let game = Game::from_placement_map(
+            &pieces,
+            Color::Black,
+            CastleRights::None,
+            CastleRights::None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(game.fen(), "4k3/8/8/8/8/8/4Q3/4K3 b - - 0 1");
+        assert!(game.is_in_check());
+        // The king has to leave the e file
+        let moves = MoveGenerator::new(&game).all_legal_moves(Color::Black);
+        assert_eq!(moves.len(), 4);
+        assert!(moves.iter().all(|mov| mov.to.file() != 4));
+
+        pieces.remove(&Position::E8);
+        let no_king = Game::from_placement_map(
+            &pieces,
+            Color::White,
+            CastleRights::None,
+            CastleRights::None,
+            None,
+        );
+        assert!(no_king.is_err());
+        pieces.insert(Position::A8, Piece::new(PieceType::King, Color::Black));
+        pieces.insert(Position::H8, Piece::new(PieceType::King, Color::Black));
+        let two_kings = Game::from_placement_map(
+            &pieces,
+            Color::White,
+            CastleRights::None,
+            CastleRights::None,
+            None,
+        );
+        assert!(two_kings.is_err());
+    }
 }
@@ -3,16 +3,20 @@
 pub mod bitboards;
 pub mod board;
 pub mod color;
+pub mod epd;
 pub mod fen;
 pub mod game;
 pub mod move_generation;
 pub mod moves;
 pub mod perft;
+pub mod pgn;
 pub mod piece;
 pub mod piece_type;
 pub mod play_game;
 pub mod players;
 pub mod position;
 pub mod print_board;
+pub mod san;
 pub mod scoped_timer;
 pub mod search;
+pub mod zobrist;
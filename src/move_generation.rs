@@ -1,4 +1,10 @@
+use std::{
+    cell::OnceCell,
+    collections::{BTreeMap, HashMap},
+};
+
 use crate::{
+    bitboards::{Bitboard, KING_DIRECTIONS},
     color::Color,
     game::Game,
     moves::{Move, MoveType, PromotionType},
@@ -8,31 +14,170 @@ use crate::{
 
 pub struct MoveGenerator<'a> {
     game: &'a Game,
+    white_legality: OnceCell<LegalityInfo<'a>>,
+    black_legality: OnceCell<LegalityInfo<'a>>,
+}
+
+/// Everything needed to filter pseudo legal moves of one color.
+/// Fetched once per generator and color when first needed, so the legality check doesn't have to do it for every move.
+struct LegalityInfo<'a> {
+    enemy_attacks: Bitboard,
+    pinned: &'a [Bitboard],
+    blockable_checks: &'a [Bitboard],
+    king: Position,
+}
+
+impl<'a> LegalityInfo<'a> {
+    fn new(game: &'a Game, color: Color) -> Self {
+        let bitboards = game.bitboards();
+        Self {
+            enemy_attacks: bitboards.attacks(color.opposite()),
+            pinned: bitboards.pinned(color),
+            blockable_checks: bitboards.blockable_checks(color),
+            king: bitboards.king(color),
+        }
+    }
+
+    fn in_check(&self) -> bool {
+        self.enemy_attacks.contains(&self.king)
+    }
+
+    /// Returns the fields a non king piece at `from` may move to.
+    /// A pinned piece stays on its pin ray and in check it has to land on the check ray.
+    /// Doesn't apply to en passant captures, they take a pawn that is not on the target field.
+    fn allowed_destinations(&self, from: &Position) -> Bitboard {
+        let mut allowed = !Bitboard::default();
+        if let Some(pin) = self.pinned.iter().find(|pin| pin.contains(from)) {
+            allowed = *pin;
+        }
+        if self.in_check() {
+            allowed = match self.blockable_checks {
+                [ray] => allowed & *ray,
+                // On double check only the king can move
+                _ => Bitboard::default(),
+            };
+        }
+        allowed
+    }
+
+    /// Whether the move lands on the check ray, blocking the check or capturing the checking piece.
+    /// An en passant capture can also take a checking pawn, which stands next to the target field.
+    fn blocks_check(&self, mov: &Move) -> bool {
+        let [ray] = self.blockable_checks else {
+            // On double check only the king can move
+            return false;
+        };
+        ray.contains(&mov.to)
+            || (mov.move_type == MoveType::EnPassantCapture
+                && ray.contains(&Position::new_unchecked(mov.to.file(), mov.from.rank())))
+    }
 }
 
 impl<'a> MoveGenerator<'a> {
     pub fn new(game: &'a Game) -> Self {
-        Self { game }
+        Self {
+            game,
+            white_legality: OnceCell::new(),
+            black_legality: OnceCell::new(),
+        }
+    }
+
+    fn legality(&self, color: Color) -> &LegalityInfo<'a> {
+        let cell = match color {
+            Color::White => &self.white_legality,
+            Color::Black => &self.black_legality,
+        };
+        cell.get_or_init(|| LegalityInfo::new(self.game, color))
     }
 }
 
 // Legal moves
 impl MoveGenerator<'_> {
     pub fn all_legal_moves(&self, color: Color) -> Vec<Move> {
-        self.game
-            .bitboards()
-            .pieces(color)
-            .iter()
-            .flat_map(|position| self.legal_moves(&position))
-            .collect()
+        let mut moves = Vec::new();
+        self.all_legal_moves_into(color, &mut moves);
+        moves
+    }
+
+    /// Appends all legal moves of the color to the list, so the caller can reuse its allocation.
+    /// Same moves in the same order as [MoveGenerator::all_legal_moves].
+    pub fn all_legal_moves_into(&self, color: Color, moves: &mut Vec<Move>) {
+        if self.legality(color).in_check() {
+            self.legal_evasions_into(color, moves);
+            return;
+        }
+        for position in self.game.bitboards().pieces(color).iter() {
+            self.legal_moves_into(&position, moves);
+        }
+    }
+
+    /// Returns all legal moves of the color, while its king is in check.
+    /// Only king moves and, for a single check, moves onto the check ray are generated.
+    pub fn legal_evasions(&self, color: Color) -> Vec<Move> {
+        let mut moves = Vec::new();
+        self.legal_evasions_into(color, &mut moves);
+        moves
+    }
+
+    fn legal_evasions_into(&self, color: Color, moves: &mut Vec<Move>) {
+        let legality = self.legality(color);
+        debug_assert!(legality.in_check(), "Evasions are only generated in check");
+        if legality.blockable_checks.len() > 1 {
+            self.legal_moves_into(&legality.king, moves);
+            return;
+        }
+        for position in self.game.bitboards().pieces(color).iter() {
+            if position == legality.king {
+                self.legal_moves_into(&position, moves);
+                continue;
+            }
+            // A pinned piece has to stay between its king and the pinning piece, never on the check ray
+            if legality.pinned.iter().any(|pin| pin.contains(&position)) {
+                continue;
+            }
+            moves.extend(
+                self.pseudo_legal_moves(&position)
+                    .into_iter()
+                    .filter(|mov| match mov.move_type {
+                        MoveType::EnPassantCapture => self.is_move_legal(mov),
+                        _ => legality.blocks_check(mov),
+                    }),
+            );
+        }
     }
 
     /// Returns all legal moves for a piece at the given position.
+    /// Pins and checks are applied as a mask on the destination, only king moves
+    /// and en passant captures go through the full legality check.
     pub fn legal_moves(&self, position: &Position) -> Vec<Move> {
-        self.pseudo_legal_moves(position)
-            .into_iter()
-            .filter(|mov| self.is_move_legal(mov))
-            .collect()
+        let mut moves = Vec::new();
+        self.legal_moves_into(position, &mut moves);
+        moves
+    }
+
+    fn legal_moves_into(&self, position: &Position, moves: &mut Vec<Move>) {
+        let Some(piece) = self.game.board().piece_at(position) else {
+            return;
+        };
+        let legality = self.legality(piece.color());
+        let pseudo_legal = self.pseudo_legal_moves(position);
+        if *position == legality.king {
+            moves.extend(
+                pseudo_legal
+                    .into_iter()
+                    .filter(|mov| self.is_move_legal(mov)),
+            );
+            return;
+        }
+
+        let allowed = legality.allowed_destinations(position);
+        moves.extend(pseudo_legal.into_iter().filter(|mov| {
+            if mov.move_type == MoveType::EnPassantCapture {
+                self.is_move_legal(mov)
+            } else {
+                allowed.contains(&mov.to)
+            }
+        }));
     }
 
     fn is_move_legal(&self, mov: &Move) -> bool {
@@ -42,21 +187,24 @@ impl MoveGenerator<'_> {
         };
 
         let to_move_color = piece_to_move.color();
+        let legality = self.legality(to_move_color);
 
-        let enemy_attacks = self.game.bitboards().attacks(to_move_color.opposite());
+        let enemy_attacks = legality.enemy_attacks;
 
         // Check pins
-        let pinned = self.game.bitboards().pinned(to_move_color);
-        if let Some(pinned) = pinned.iter().find(|board| board.contains(&mov.from)) {
+        if let Some(pinned) = legality
+            .pinned
+            .iter()
+            .find(|board| board.contains(&mov.from))
+        {
             // The piece we move is pinned
-            // We can only move in the pin
-            return pinned.contains(&mov.to);
+            // We can only move in the pin, and still have to block a check
+            return pinned.contains(&mov.to)
+                && (!legality.in_check() || legality.blocks_check(mov));
         }
 
         // Check if we are in check and need to block. Moving out should be checked be the king movement
-        let blockable_checks = self.game.bitboards().blockable_checks(to_move_color);
-
-        if enemy_attacks.contains(&self.game.bitboards().king(to_move_color)) {
+        if legality.in_check() {
             // We are currently in check. We need to block, or move the king out of the way
             match piece_to_move.piece_type() {
                 PieceType::King => {
@@ -65,13 +213,11 @@ impl MoveGenerator<'_> {
                         return false;
                     }
                 }
-                _ if blockable_checks.len() == 1 => {
-                    let blockable = blockable_checks[0];
-                    return blockable.contains(&mov.to);
-                }
                 _ => {
-                    // We cant block, because more than one piece is attacking or we are attacked be a knight or pawn
-                    return false;
+                    // Block or capture the checking piece. More than one checking piece can't be blocked
+                    if !legality.blocks_check(mov) {
+                        return false;
+                    }
                 }
             }
         }
@@ -101,6 +247,12 @@ impl MoveGenerator<'_> {
                     }
                 }
 
+                // Hand built castle moves did not go through the pseudo legal generation,
+                // so the rook and the empty fields need to be checked again
+                if self.game.castle_rook_move(mov).is_err() {
+                    return false;
+                }
+
                 let all_to_check = [
                     Position::new_unchecked(mov.from.file(), root_rank),
                     Position::new_unchecked(
@@ -127,7 +279,7 @@ impl MoveGenerator<'_> {
             // Need updated pieces bitboard
 
             // First we check if the king is even next to us
-            let king_pos = self.game.bitboards().king(to_move_color);
+            let king_pos = legality.king;
             if king_pos.rank() == mov.from.rank() {
                 // Get side on which the pawn is
                 let pawn_dir = mov.from.rank_direction(&king_pos);
@@ -164,6 +316,47 @@ impl MoveGenerator<'_> {
     }
 }
 
+// Pins
+impl MoveGenerator<'_> {
+    /// Returns all pins against the piece at the given position.
+    /// Each pin is a ray from the pinned piece (inclusive) to the pinning slider (inclusive).
+    /// Works for any piece, so it also finds relative pins (e.g. a knight pinned to the queen).
+    pub fn pins_to(&self, position: &Position) -> Vec<Bitboard> {
+        let board = self.game.board();
+        let Some(target) = board.piece_at(position) else {
+            return Vec::new();
+        };
+
+        let mut pins = Vec::new();
+        for (dx, dy) in KING_DIRECTIONS {
+            let mut ray = Bitboard::default();
+            let mut found_friendly = false;
+            let mut current = *position;
+            while let Some(next) = current.offset(dx, dy) {
+                current = next;
+                ray |= Bitboard::from(current);
+
+                let Some(piece) = board.piece_at(&current) else {
+                    continue;
+                };
+                if piece.color() == target.color() {
+                    if found_friendly {
+                        // Two pieces in between, no pin possible
+                        break;
+                    }
+                    found_friendly = true;
+                } else {
+                    if found_friendly && piece.piece_type().slides_in_direction(dx, dy) {
+                        pins.push(ray);
+                    }
+                    break;
+                }
+            }
+        }
+        pins
+    }
+}
+
 // Pseudo legal moves are moves that are legal in terms of the rules of chess, but may not be legal
 impl MoveGenerator<'_> {
     /// Returns all possible attacking moves for a piece at the given position.
@@ -325,14 +518,10 @@ impl MoveGenerator<'_> {
     }
 
     fn pawn_possible_attacking_moves(&self, position: &Position, color: Color) -> Vec<Move> {
-        let dir = color.board_direction();
-        let mut res = Vec::with_capacity(2);
-        for &dx in &[-1, 1] {
-            if let Some(new_pos) = position.offset(dx, dir) {
-                res.push(Move::new(*position, new_pos, MoveType::Quiet));
-            }
-        }
-        res
+        Bitboard::pawn_attacks(color, position)
+            .iter()
+            .map(|new_pos| Move::new(*position, new_pos, MoveType::Quiet))
+            .collect()
     }
 
     fn knight_pseudo_legal_moves(
@@ -343,23 +532,17 @@ impl MoveGenerator<'_> {
     ) -> Vec<Move> {
         let board = self.game.board();
         let mut result = Vec::new();
-        for &dx in &[-2i8, -1, 1, 2] {
-            for &dy in &[-2i8, -1, 1, 2] {
-                if dx.abs() != dy.abs() {
-                    if let Some(new_pos) = position.offset(dx, dy) {
-                        if let Some(piece) = board.piece_at(&new_pos) {
-                            if piece.color() != color || friendly_attacks {
-                                result.push(Move::new(
-                                    *position,
-                                    new_pos,
-                                    MoveType::Capture(piece.piece_type()),
-                                ));
-                            }
-                        } else {
-                            result.push(Move::new(*position, new_pos, MoveType::Quiet));
-                        }
-                    }
+        for new_pos in Bitboard::knight_attacks(position).iter() {
+            if let Some(piece) = board.piece_at(&new_pos) {
+                if piece.color() != color || friendly_attacks {
+                    result.push(Move::new(
+                        *position,
+                        new_pos,
+                        MoveType::Capture(piece.piece_type()),
+                    ));
                 }
+            } else {
+                result.push(Move::new(*position, new_pos, MoveType::Quiet));
             }
         }
         result
@@ -475,23 +658,17 @@ impl MoveGenerator<'_> {
     ) -> Vec<Move> {
         let board = self.game.board();
         let mut result = Vec::new();
-        for &dx in &[-1, 0, 1] {
-            for &dy in &[-1, 0, 1] {
-                if dx != 0 || dy != 0 {
-                    if let Some(new_pos) = position.offset(dx, dy) {
-                        if let Some(piece) = board.piece_at(&new_pos) {
-                            if piece.color() != color || frindly_attacks {
-                                result.push(Move::new(
-                                    *position,
-                                    new_pos,
-                                    MoveType::Capture(piece.piece_type()),
-                                ));
-                            }
-                        } else {
-                            result.push(Move::new(*position, new_pos, MoveType::Quiet));
-                        }
-                    }
+        for new_pos in Bitboard::king_attacks(position).iter() {
+            if let Some(piece) = board.piece_at(&new_pos) {
+                if piece.color() != color || frindly_attacks {
+                    result.push(Move::new(
+                        *position,
+                        new_pos,
+                        MoveType::Capture(piece.piece_type()),
+                    ));
                 }
+            } else {
+                result.push(Move::new(*position, new_pos, MoveType::Quiet));
             }
         }
 
@@ -544,10 +721,92 @@ impl MoveGenerator<'_> {
     }
 }
 
+/// Remembers the legal moves of recently seen positions by their zobrist hash.
+/// When full, the least recently used position is dropped.
+pub struct MoveCache {
+    capacity: usize,
+    // The moves of a position and the last time they were used
+    entries: HashMap<u64, (Vec<Move>, u64)>,
+    // Positions ordered by the last time they were used
+    usage: BTreeMap<u64, u64>,
+    time: u64,
+    hits: u32,
+    misses: u32,
+}
+
+impl MoveCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: HashMap::new(),
+            usage: BTreeMap::new(),
+            time: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Returns all legal moves of the side to move, generating them only if the position isn't cached.
+    pub fn all_legal_moves(&mut self, game: &Game) -> &[Move] {
+        self.all_legal_moves_with_hash(game, game.hash())
+    }
+
+    /// Like [MoveCache::all_legal_moves], for callers that already know the hash of the position.
+    pub fn all_legal_moves_with_hash(&mut self, game: &Game, hash: u64) -> &[Move] {
+        self.time += 1;
+        if let Some((_, last_used)) = self.entries.get_mut(&hash) {
+            self.hits += 1;
+            self.usage.remove(last_used);
+            *last_used = self.time;
+        } else {
+            self.misses += 1;
+            if self.entries.len() >= self.capacity {
+                if let Some((_, oldest)) = self.usage.pop_first() {
+                    self.entries.remove(&oldest);
+                }
+            }
+            let moves = MoveGenerator::new(game).all_legal_moves(game.current_turn());
+            self.entries.insert(hash, (moves, self.time));
+        }
+        self.usage.insert(self.time, hash);
+        &self.entries[&hash].0
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn hits(&self) -> u32 {
+        self.hits
+    }
+
+    pub fn misses(&self) -> u32 {
+        self.misses
+    }
+
+    /// Share of the lookups served from the cache, 0 without lookups.
+    pub fn hit_rate(&self) -> f64 {
+        let lookups = self.hits + self.misses;
+        if lookups == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / lookups as f64
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{board::Board, fen::Fen, game::CastleRights, piece::Piece};
+    use crate::{
+        board::Board,
+        fen::Fen,
+        game::{CastleRights, GameBuilder},
+        piece::Piece,
+    };
 
     #[test]
     fn test_pawn_pseudo_legal_moves() {
@@ -571,19 +830,11 @@ mod tests {
     #[test]
     fn test_knight_pseudo_legal_moves() {
         let knight_position = Position::new_unchecked(3, 3);
-        let mut board = Board::default();
-        board.place_piece(
-            Piece::new(PieceType::Knight, Color::White),
-            &knight_position,
-        );
-
-        let game = Game::new(
-            board,
-            Color::White,
-            CastleRights::Both,
-            CastleRights::Both,
-            None,
-        );
+        let game = GameBuilder::default()
+            .piece(knight_position, Piece::new(PieceType::Knight, Color::White))
+            .castle_rights(Color::White, CastleRights::Both)
+            .castle_rights(Color::Black, CastleRights::Both)
+            .build();
 
         let move_generator = MoveGenerator::new(&game);
         let moves = move_generator.pseudo_legal_moves(&knight_position);
@@ -593,19 +844,12 @@ mod tests {
     #[test]
     fn test_bishop_pseudo_legal_moves() {
         let bishop_position = Position::new_unchecked(3, 3);
-        let mut board = Board::default();
-        board.place_piece(
-            Piece::new(PieceType::Bishop, Color::White),
-            &bishop_position,
-        );
+        let game = GameBuilder::default()
+            .piece(bishop_position, Piece::new(PieceType::Bishop, Color::White))
+            .castle_rights(Color::White, CastleRights::Both)
+            .castle_rights(Color::Black, CastleRights::Both)
+            .build();
 
-        let game = Game::new(
-            board,
-            Color::White,
-            CastleRights::Both,
-            CastleRights::Both,
-            None,
-        );
         let move_generator = MoveGenerator::new(&game);
 
         let moves = move_generator.pseudo_legal_moves(&bishop_position);
@@ -615,17 +859,12 @@ mod tests {
     #[test]
     fn test_rook_pseudo_legal_moves() {
         let rook_position = Position::new_unchecked(3, 3);
+        let game = GameBuilder::default()
+            .piece(rook_position, Piece::new(PieceType::Rook, Color::White))
+            .castle_rights(Color::White, CastleRights::Both)
+            .castle_rights(Color::Black, CastleRights::Both)
+            .build();
 
-        let mut board = Board::default();
-        board.place_piece(Piece::new(PieceType::Rook, Color::White), &rook_position);
-
-        let game = Game::new(
-            board,
-            Color::White,
-            CastleRights::Both,
-            CastleRights::Both,
-            None,
-        );
         let move_generator = MoveGenerator::new(&game);
 
         let moves = move_generator.pseudo_legal_moves(&rook_position);
@@ -635,17 +874,12 @@ mod tests {
     #[test]
     fn test_queen_pseudo_legal_moves() {
         let queen_position = Position::new_unchecked(3, 3);
+        let game = GameBuilder::default()
+            .piece(queen_position, Piece::new(PieceType::Queen, Color::White))
+            .castle_rights(Color::White, CastleRights::Both)
+            .castle_rights(Color::Black, CastleRights::Both)
+            .build();
 
-        let mut board = Board::default();
-        board.place_piece(Piece::new(PieceType::Queen, Color::White), &queen_position);
-
-        let game = Game::new(
-            board,
-            Color::White,
-            CastleRights::Both,
-            CastleRights::Both,
-            None,
-        );
         let move_generator = MoveGenerator::new(&game);
 
         let moves = move_generator.pseudo_legal_moves(&queen_position);
@@ -655,17 +889,12 @@ mod tests {
     #[test]
     fn test_king_pseudo_legal_moves() {
         let king_position = Position::new_unchecked(3, 3);
+        let game = GameBuilder::default()
+            .piece(king_position, Piece::new(PieceType::King, Color::White))
+            .castle_rights(Color::White, CastleRights::Both)
+            .castle_rights(Color::Black, CastleRights::Both)
+            .build();
 
-        let mut board = Board::default();
-        board.place_piece(Piece::new(PieceType::King, Color::White), &king_position);
-
-        let game = Game::new(
-            board,
-            Color::White,
-            CastleRights::Both,
-            CastleRights::Both,
-            None,
-        );
         let move_generator = MoveGenerator::new(&game);
 
         let moves = move_generator.pseudo_legal_moves(&king_position);
@@ -676,7 +905,7 @@ mod tests {
     fn legal_move_pin() {
         let fen = "8/8/3p4/K1pP3r/4Rp1k/8/4P1P1/8 b - c6 0 1";
 
-        test_legal_moves(fen, 0, &&Position::F4);
+        test_legal_moves(fen, 0, &Position::F4);
     }
 
     #[test]
@@ -739,10 +968,254 @@ mod tests {
         }
     }
 
+    #[test]
+    fn legal_moves_match_per_piece_generation() {
+        for (fen, expected_moves) in [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                20,
+            ),
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                48,
+            ),
+            ("8/8/3p4/K1pP3r/4R2k/5p2/4P1P1/8 b - - 0 1", 2),
+            // In check, these are generated as evasions
+            ("4k3/8/8/8/8/8/3q4/R3K2R w KQ - 0 1", 2),
+            ("4k3/8/3N4/8/8/8/8/4R1K1 b - - 0 1", 3),
+            ("4k3/4r3/8/8/8/2N5/8/R3K3 w Q - 0 1", 6),
+            ("8/8/8/3pP3/4K3/8/8/7k w - d6 0 1", 8),
+        ] {
+            let game = Fen::parse_game(fen).unwrap();
+            let move_generator = MoveGenerator::new(&game);
+            let color = game.current_turn();
+
+            let all_moves = move_generator.all_legal_moves(color);
+            assert_eq!(all_moves.len(), expected_moves);
+
+            // Reusing the generator must give the same result as a fresh one per piece
+            let per_piece = game
+                .bitboards()
+                .pieces(color)
+                .iter()
+                .flat_map(|position| MoveGenerator::new(&game).legal_moves(&position))
+                .collect::<Vec<_>>();
+            assert_eq!(all_moves, per_piece);
+            assert_eq!(all_moves, move_generator.all_legal_moves(color));
+
+            // Filling a list appends to it
+            let mut buffer = vec![Move::new(Position::A1, Position::A2, MoveType::Quiet)];
+            move_generator.all_legal_moves_into(color, &mut buffer);
+            assert_eq!(buffer[1..], all_moves);
+            if game.is_in_check() {
+                assert_eq!(move_generator.legal_evasions(color), per_piece);
+            }
+        }
+    }
+
+    #[test]
+    fn en_passant_captures_checking_pawn() {
+        let game = Fen::parse_game("8/8/8/3pP3/4K3/8/8/7k w - d6 0 1").unwrap();
+        let moves = MoveGenerator::new(&game).all_legal_moves(Color::White);
+        assert!(moves.contains(&Move::new(
+            Position::E5,
+            Position::D6,
+            MoveType::EnPassantCapture
+        )));
+        // Only the en passant capture takes the checker, no other pawn move is legal
+        assert_eq!(
+            moves.iter().filter(|mov| mov.from == Position::E5).count(),
+            1
+        );
+    }
+
+    #[test]
+    fn blocked_pawn_pushes() {
+        // A piece right in front blocks both pushes, one two fields ahead only the double push
+        for (fen, from, pushes) in [
+            ("4k3/8/8/8/8/4N3/4P3/4K3 w - - 0 1", Position::E2, 0),
+            ("4k3/8/8/8/8/4n3/4P3/4K3 w - - 0 1", Position::E2, 0),
+            ("4k3/3p4/3B4/8/8/8/8/4K3 b - - 0 1", Position::D7, 0),
+            ("4k3/8/8/8/4n3/8/4P3/4K3 w - - 0 1", Position::E2, 1),
+            ("4k3/3p4/8/3B4/8/8/8/4K3 b - - 0 1", Position::D7, 1),
+        ] {
+            let game = Fen::parse_game(fen).unwrap();
+            let moves = MoveGenerator::new(&game).pseudo_legal_moves(&from);
+            assert_eq!(moves.len(), pushes, "{fen}");
+            assert!(
+                moves.iter().all(|mov| mov.move_type == MoveType::Quiet),
+                "{fen}"
+            );
+        }
+    }
+
+    #[test]
+    fn promotion_captures() {
+        for (fen, from, to) in [
+            ("1n6/P7/8/8/8/8/8/K6k w - - 0 1", Position::A7, Position::B8),
+            ("K6k/8/8/8/8/8/p7/1N6 b - - 0 1", Position::A2, Position::B1),
+        ] {
+            let game = Fen::parse_game(fen).unwrap();
+            let moves = MoveGenerator::new(&game).legal_moves(&from);
+            assert_eq!(moves.len(), 8);
+
+            let captures = moves
+                .iter()
+                .filter_map(|mov| match mov.move_type {
+                    MoveType::PromotionCapture(promotion, captured) => {
+                        assert_eq!(mov.to, to);
+                        Some((promotion, captured))
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(captures.len(), 4);
+            for promotion in [
+                PromotionType::Queen,
+                PromotionType::Rook,
+                PromotionType::Bishop,
+                PromotionType::Knight,
+            ] {
+                assert!(captures.contains(&(promotion, PieceType::Knight)));
+            }
+        }
+    }
+
+    #[test]
+    fn knight_pinned_to_queen() {
+        let game = Fen::parse_game("4k3/8/8/7b/8/8/4N3/3QK3 w - - 0 1").unwrap();
+        let move_generator = MoveGenerator::new(&game);
+
+        let pins = move_generator.pins_to(&Position::D1);
+        assert_eq!(pins.len(), 1);
+        assert!(pins[0].contains(&Position::E2));
+        assert!(pins[0].contains(&Position::H5));
+        assert!(!pins[0].contains(&Position::D1));
+
+        // The knight is not pinned to the king
+        assert!(move_generator.pins_to(&Position::E1).is_empty());
+        assert!(game.bitboards().pinned(Color::White).is_empty());
+
+        // Nothing in between is an attack, not a pin
+        let game = Fen::parse_game("4k3/8/8/7b/8/8/8/3QK3 w - - 0 1").unwrap();
+        assert!(MoveGenerator::new(&game).pins_to(&Position::D1).is_empty());
+    }
+
+    #[test]
+    fn hand_built_castle_needs_rook_and_free_path() {
+        // Castle rights are set, but there are no rooks on the board
+        let game = GameBuilder::default()
+            .piece(Position::E1, Piece::new(PieceType::King, Color::White))
+            .piece(Position::E8, Piece::new(PieceType::King, Color::Black))
+            .castle_rights(Color::White, CastleRights::Both)
+            .build();
+        let move_generator = MoveGenerator::new(&game);
+        let king_side = Move::new(Position::E1, Position::G1, MoveType::Castle);
+        let queen_side = Move::new(Position::E1, Position::C1, MoveType::Castle);
+        assert!(!move_generator.is_move_legal(&king_side));
+        assert!(!move_generator.is_move_legal(&queen_side));
+
+        // Queen side rook is there, but the knight on b1 blocks
+        let game = Fen::parse_game("4k3/8/8/8/8/8/8/RN2K2R w KQ - 0 1").unwrap();
+        let move_generator = MoveGenerator::new(&game);
+        assert!(move_generator.is_move_legal(&king_side));
+        assert!(!move_generator.is_move_legal(&queen_side));
+    }
+
+    // The original generation, filtering every pseudo legal move through the full legality check
+    fn reference_legal_moves(move_generator: &MoveGenerator, color: Color) -> Vec<Move> {
+        move_generator
+            .game
+            .bitboards()
+            .pieces(color)
+            .iter()
+            .flat_map(|position| move_generator.pseudo_legal_moves(&position))
+            .filter(|mov| move_generator.is_move_legal(mov))
+            .collect()
+    }
+
+    fn assert_same_as_reference(game: &mut Game, depth: usize) {
+        let color = game.current_turn();
+        let moves = {
+            let move_generator = MoveGenerator::new(game);
+            let moves = move_generator.all_legal_moves(color);
+            assert_eq!(
+                moves,
+                reference_legal_moves(&move_generator, color),
+                "{game}"
+            );
+            moves
+        };
+        if depth == 0 {
+            return;
+        }
+        for mov in moves {
+            game.make_move(mov).unwrap();
+            assert_same_as_reference(game, depth - 1);
+            game.unmake_move();
+        }
+    }
+
+    #[test]
+    fn legal_moves_match_reference_on_perft_suite() {
+        for (fen, depth) in [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                2,
+            ),
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                2,
+            ),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 3),
+            // The checking pawn can be captured en passant
+            ("8/8/8/3pP3/4K3/8/8/7k w - d6 0 1", 1),
+            (
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+                2,
+            ),
+            (
+                "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+                2,
+            ),
+            (
+                "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+                2,
+            ),
+        ] {
+            let mut game = Fen::parse_game(fen).unwrap();
+            assert_same_as_reference(&mut game, depth);
+        }
+    }
+
     fn test_legal_moves(fen: &str, expected_moves: usize, piece_to_check: &Position) {
         let game = Fen::parse_game(fen).unwrap();
         let move_generator = MoveGenerator::new(&game);
         let legal_moves = move_generator.legal_moves(piece_to_check);
         assert_eq!(legal_moves.len(), expected_moves);
     }
+
+    #[test]
+    fn move_cache() {
+        let mut cache = MoveCache::new(2);
+        let mut game = Game::default();
+        let fresh = MoveGenerator::new(&game).all_legal_moves(Color::White);
+        assert_eq!(cache.all_legal_moves(&game), fresh.as_slice());
+        assert_eq!((cache.hits(), cache.misses()), (0, 1));
+        assert_eq!(cache.all_legal_moves(&game), fresh.as_slice());
+        assert_eq!((cache.hits(), cache.misses()), (1, 1));
+
+        let start = game.clone();
+        for mov in ["g1f3", "g8f6"] {
+            let mov = game.resolve_move(&mov.parse().unwrap()).unwrap();
+            game.make_move(mov).unwrap();
+            let fresh = MoveGenerator::new(&game).all_legal_moves(game.current_turn());
+            assert_eq!(cache.all_legal_moves(&game), fresh.as_slice());
+        }
+        // The start position was used least recently and got dropped
+        assert_eq!(cache.len(), 2);
+        cache.all_legal_moves(&start);
+        assert_eq!((cache.hits(), cache.misses()), (1, 4));
+        assert_eq!(cache.hit_rate(), 0.2);
+    }
 }
@@ -36,6 +36,19 @@ impl Move {
             move_type,
         }
     }
+
+    /// Returns the move in uci long algebraic notation.
+    /// Castling is written as the two square king move (`e1g1`), en passant as the pawn move
+    /// and promotions always carry the lowercase promotion piece (`e7f8q`).
+    pub fn to_uci(&self) -> String {
+        let promotion = match &self.move_type {
+            MoveType::PromotionQuite(promotion) | MoveType::PromotionCapture(promotion, _) => {
+                promotion.to_string()
+            }
+            _ => "".to_string(),
+        };
+        format!("{}{}{}", self.from, self.to, promotion)
+    }
 }
 
 impl FromStr for Move {
@@ -56,15 +69,29 @@ impl FromStr for Move {
     }
 }
 
+impl TryFrom<&str> for Move {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::from_str(value)
+    }
+}
+
 impl Display for Move {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let promotion = match &self.move_type {
-            MoveType::PromotionQuite(promotion) | MoveType::PromotionCapture(promotion, _) => {
-                format!("{}", promotion)
-            }
-            _ => "".to_string(),
-        };
-        write!(f, "{}{}{}", self.from, self.to, promotion)
+        write!(f, "{}", self.to_uci())
+    }
+}
+
+impl From<&Move> for String {
+    fn from(value: &Move) -> Self {
+        value.to_uci()
+    }
+}
+
+impl From<Move> for String {
+    fn from(value: Move) -> Self {
+        value.to_uci()
     }
 }
 
@@ -192,4 +219,52 @@ mod tests {
         );
         assert_eq!(mov.to_string(), "e7e8n");
     }
+
+    #[test]
+    fn test_move_to_uci() {
+        use super::{Move, MoveType, Position, PromotionType};
+        use crate::piece_type::PieceType;
+
+        let mov = Move::new(Position::E1, Position::G1, MoveType::Castle);
+        assert_eq!(mov.to_uci(), "e1g1");
+        let mov = Move::new(Position::E8, Position::C8, MoveType::Castle);
+        assert_eq!(mov.to_uci(), "e8c8");
+
+        let mov = Move::new(Position::E5, Position::D6, MoveType::EnPassantCapture);
+        assert_eq!(mov.to_uci(), "e5d6");
+
+        let mov = Move::new(
+            Position::E7,
+            Position::F8,
+            MoveType::PromotionCapture(PromotionType::Knight, PieceType::Rook),
+        );
+        assert_eq!(mov.to_uci(), "e7f8n");
+
+        let mov = Move::new(
+            Position::B2,
+            Position::A1,
+            MoveType::PromotionCapture(PromotionType::Queen, PieceType::Rook),
+        );
+        assert_eq!(mov.to_uci(), "b2a1q");
+        assert_eq!(mov.to_uci(), mov.to_string());
+    }
+
+    #[test]
+    fn test_move_conversions() {
+        use super::{Move, MoveType, Position, PromotionType};
+
+        let mov = Move::try_from("e2e4").unwrap();
+        assert_eq!(mov, Move::new(Position::E2, Position::E4, MoveType::Quiet));
+        assert_eq!(String::from(&mov), "e2e4");
+
+        let mov = Move::try_from("a7a8q").unwrap();
+        let uci: String = mov.into();
+        assert_eq!(Move::try_from(uci.as_str()).unwrap(), mov);
+        assert_eq!(
+            mov.move_type,
+            MoveType::PromotionQuite(PromotionType::Queen)
+        );
+
+        assert!(Move::try_from("e2").is_err());
+    }
 }
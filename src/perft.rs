@@ -7,15 +7,41 @@ use std::{
 
 use anyhow::Context;
 
-use crate::{fen::Fen, game::Game, move_generation::MoveGenerator};
+use crate::{
+    fen::Fen,
+    game::Game,
+    move_generation::MoveGenerator,
+    moves::{Move, MoveType},
+};
 
 #[derive(Default, Debug)]
 pub struct PerfTestResults {
     nodes: HashMap<String, u64>,
     node_count: u64,
+    // Counted for the moves at the last depth only
+    captures: u64,
+    en_passants: u64,
+    castles: u64,
+    promotions: u64,
+    checks: u64,
 }
 
 impl PerfTestResults {
+    fn count_move(&mut self, mov: &Move, gives_check: bool) {
+        if mov.move_type.is_capture() {
+            self.captures += 1;
+        }
+        match mov.move_type {
+            MoveType::EnPassantCapture => self.en_passants += 1,
+            MoveType::Castle => self.castles += 1,
+            MoveType::PromotionQuite(_) | MoveType::PromotionCapture(_, _) => self.promotions += 1,
+            _ => {}
+        }
+        if gives_check {
+            self.checks += 1;
+        }
+    }
+
     pub fn show_diff(&self, other: &PerfTestResults) {
         for (mov, nodes) in self.nodes.iter() {
             if let Some(stockfish_nodes) = other.nodes.get(mov) {
@@ -45,6 +71,27 @@ impl PerfTestResults {
     }
 }
 
+/// Counts the leaf nodes of the game tree up to the given depth.
+/// The game is restored after counting.
+pub fn count(game: &mut Game, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let moves = MoveGenerator::new(game).all_legal_moves(game.current_turn());
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for mov in moves {
+        game.make_move(mov)
+            .unwrap_or_else(|e| panic!("Failed to make move {mov}: {e}"));
+        nodes += count(game, depth - 1);
+        game.unmake_move();
+    }
+    nodes
+}
+
 pub struct PerfTest {
     game: Game,
     depth: usize,
@@ -101,10 +148,7 @@ impl PerfTest {
     }
 
     pub fn run_perft(&mut self) -> PerfTestResults {
-        let mut result = PerfTestResults {
-            nodes: HashMap::new(),
-            node_count: 0,
-        };
+        let mut result = PerfTestResults::default();
 
         let move_generator = MoveGenerator::new(&self.game);
         let current_color = self.game.current_turn();
@@ -124,7 +168,10 @@ impl PerfTest {
             }
             //self.game.print_pieces();
             //std::thread::sleep(std::time::Duration::from_millis(50));
-            let nodes = self.step(1);
+            if self.depth <= 1 {
+                result.count_move(&mov, self.game.is_in_check());
+            }
+            let nodes = self.step(1, &mut result);
             result.nodes.insert(mov.to_string(), nodes);
             result.node_count += nodes;
 
@@ -134,7 +181,7 @@ impl PerfTest {
         result
     }
 
-    fn step(&mut self, cur_depth: usize) -> u64 {
+    fn step(&mut self, cur_depth: usize, result: &mut PerfTestResults) -> u64 {
         if cur_depth >= self.depth {
             return 1;
         }
@@ -157,7 +204,10 @@ impl PerfTest {
             }
             //self.game.print_pieces();
             //std::thread::sleep(std::time::Duration::from_millis(50));
-            total_moves += self.step(cur_depth + 1);
+            if cur_depth + 1 >= self.depth {
+                result.count_move(&mov, self.game.is_in_check());
+            }
+            total_moves += self.step(cur_depth + 1, result);
             self.game.unmake_move();
         }
         total_moves
@@ -167,9 +217,56 @@ impl PerfTest {
 impl Display for PerfTestResults {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "Perft results: {}", self.node_count)?;
-        for (i, res) in self.nodes.iter() {
+        writeln!(f, "Captures: {}", self.captures)?;
+        writeln!(f, "En passants: {}", self.en_passants)?;
+        writeln!(f, "Castles: {}", self.castles)?;
+        writeln!(f, "Promotions: {}", self.promotions)?;
+        writeln!(f, "Checks: {}", self.checks)?;
+        // Sorted, so the output can be compared between runs
+        let mut nodes = self.nodes.iter().collect::<Vec<_>>();
+        nodes.sort();
+        for (i, res) in nodes {
             writeln!(f, "{}: {}", i, res)?;
         }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+
+    fn run(fen: &str, depth: usize) -> PerfTestResults {
+        PerfTest::new(Fen::parse_game(fen).unwrap(), depth).run_perft()
+    }
+
+    #[test]
+    fn count_start_position() {
+        let mut game = Game::default();
+        assert_eq!(count(&mut game, 0), 1);
+        assert_eq!(count(&mut game, 1), 20);
+        assert_eq!(count(&mut game, 2), 400);
+        assert_eq!(game, Game::default());
+    }
+
+    #[test]
+    fn kiwipete_breakdown() {
+        let results = run(KIWIPETE, 1);
+        assert_eq!(results.node_count, 48);
+        assert_eq!(results.captures, 8);
+        assert_eq!(results.en_passants, 0);
+        assert_eq!(results.castles, 2);
+        assert_eq!(results.promotions, 0);
+        assert_eq!(results.checks, 0);
+
+        let results = run(KIWIPETE, 2);
+        assert_eq!(results.node_count, 2039);
+        assert_eq!(results.captures, 351);
+        assert_eq!(results.en_passants, 1);
+        assert_eq!(results.castles, 91);
+        assert_eq!(results.promotions, 0);
+        assert_eq!(results.checks, 3);
+    }
+}
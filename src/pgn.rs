@@ -0,0 +1,149 @@
+use crate::{color::Color, fen::Fen, game::Game, san::San};
+
+/// The tags written in front of the moves.
+/// The result tag is taken from the game.
+#[derive(Debug, Clone)]
+pub struct PgnTags {
+    pub event: String,
+    pub site: String,
+    /// Format `YYYY.MM.DD`, unknown parts are written as `?`
+    pub date: String,
+    pub round: String,
+    pub white: String,
+    pub black: String,
+}
+
+impl Default for PgnTags {
+    fn default() -> Self {
+        Self {
+            event: "?".to_string(),
+            site: "?".to_string(),
+            date: "????.??.??".to_string(),
+            round: "?".to_string(),
+            white: "?".to_string(),
+            black: "?".to_string(),
+        }
+    }
+}
+
+/// Portable game notation, the moves of a game with some information about it.
+pub struct Pgn;
+
+impl Pgn {
+    /// Exports all moves played in the game.
+    /// If the game didn't start from the default position, the start position is added as fen.
+    /// The move numbers continue from the fullmove number of the start position.
+    pub fn from_game(game: &Game, tags: &PgnTags) -> anyhow::Result<String> {
+        let moves = game.move_stack();
+        let mut replay = game.clone();
+        for _ in 0..moves.len() {
+            replay.unmake_move();
+        }
+
+        let result = game.result();
+        let mut pgn = String::new();
+        for (name, value) in [
+            ("Event", tags.event.as_str()),
+            ("Site", tags.site.as_str()),
+            ("Date", tags.date.as_str()),
+            ("Round", tags.round.as_str()),
+            ("White", tags.white.as_str()),
+            ("Black", tags.black.as_str()),
+            ("Result", result.to_string().as_str()),
+        ] {
+            pgn.push_str(&format!("[{name} \"{}\"]\n", escape(value)));
+        }
+        let start_fen = Fen::from_game(&replay);
+        if start_fen != Fen::from_game(&Game::default()) {
+            pgn.push_str("[SetUp \"1\"]\n");
+            pgn.push_str(&format!("[FEN \"{start_fen}\"]\n"));
+        }
+        pgn.push('\n');
+
+        let mut move_text = Vec::new();
+        for (i, mov) in moves.iter().enumerate() {
+            let move_number = replay.fullmove_number();
+            if replay.current_turn() == Color::White {
+                move_text.push(format!("{move_number}."));
+            } else if i == 0 {
+                move_text.push(format!("{move_number}..."));
+            }
+            move_text.push(San::from_move(&replay, mov)?);
+            replay.make_move(*mov)?;
+        }
+        move_text.push(result.to_string());
+        pgn.push_str(&move_text.join(" "));
+        pgn.push('\n');
+
+        Ok(pgn)
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(game: &mut Game, moves: &[&str]) {
+        for san in moves {
+            let mov = San::parse_move(game, san).unwrap();
+            game.make_move(mov).unwrap();
+        }
+    }
+
+    #[test]
+    fn seven_tag_roster() {
+        let mut game = Game::default();
+        play(&mut game, &["f3", "e5", "g4", "Qh4#"]);
+        let tags = PgnTags {
+            event: "Club \"Blitz\"".to_string(),
+            site: "Berlin".to_string(),
+            date: "2024.05.01".to_string(),
+            round: "1".to_string(),
+            white: "Alice".to_string(),
+            black: "Bob".to_string(),
+        };
+
+        let pgn = Pgn::from_game(&game, &tags).unwrap();
+        assert_eq!(
+            pgn,
+            "[Event \"Club \\\"Blitz\\\"\"]\n\
+             [Site \"Berlin\"]\n\
+             [Date \"2024.05.01\"]\n\
+             [Round \"1\"]\n\
+             [White \"Alice\"]\n\
+             [Black \"Bob\"]\n\
+             [Result \"0-1\"]\n\
+             \n\
+             1. f3 e5 2. g4 Qh4# 0-1\n"
+        );
+    }
+
+    #[test]
+    fn custom_start_position() {
+        let mut game = Fen::parse_game("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1").unwrap();
+        play(&mut game, &["Kd7", "e4"]);
+
+        let pgn = Pgn::from_game(&game, &PgnTags::default()).unwrap();
+        assert!(pgn.starts_with("[Event \"?\"]\n"));
+        assert!(pgn.contains("[Result \"*\"]\n"));
+        assert!(pgn.contains("[FEN \"4k3/8/8/8/8/8/4P3/4K3 b - - 0 1\"]\n"));
+        assert!(pgn.ends_with("\n1... Kd7 2. e4 *\n"));
+    }
+
+    #[test]
+    fn move_numbers_continue_from_fen() {
+        let mut game = Fen::parse_game("4k3/8/8/8/8/8/4P3/4K3 w - - 3 12").unwrap();
+        play(&mut game, &["e4", "Kd7", "e5"]);
+        let pgn = Pgn::from_game(&game, &PgnTags::default()).unwrap();
+        assert!(pgn.ends_with("\n12. e4 Kd7 13. e5 *\n"));
+
+        let mut game = Fen::parse_game("4k3/8/8/8/8/8/4P3/4K3 b - - 3 12").unwrap();
+        play(&mut game, &["Kd7", "e4", "Ke6"]);
+        let pgn = Pgn::from_game(&game, &PgnTags::default()).unwrap();
+        assert!(pgn.ends_with("\n12... Kd7 13. e4 Ke6 *\n"));
+    }
+}
@@ -11,6 +11,17 @@ pub enum PieceType {
 }
 
 impl PieceType {
+    /// All piece types, ordered like the enum (pawn, knight, bishop, rook, queen, king).
+    /// The index of a type in this array is `piece_type as usize`.
+    pub const ALL: [PieceType; 6] = [
+        PieceType::Pawn,
+        PieceType::Knight,
+        PieceType::Bishop,
+        PieceType::Rook,
+        PieceType::Queen,
+        PieceType::King,
+    ];
+
     pub fn value(&self) -> i32 {
         match self {
             PieceType::Pawn => 100,
@@ -21,6 +32,23 @@ impl PieceType {
             PieceType::King => 1000,
         }
     }
+
+    /// Returns true for pieces that move along rays (bishop, rook, queen)
+    pub fn is_slider(&self) -> bool {
+        matches!(self, PieceType::Bishop | PieceType::Rook | PieceType::Queen)
+    }
+
+    /// Returns true if the piece can attack along the given direction any number of squares.
+    /// The direction is a king step, e.g. (1, 0) or (-1, 1).
+    pub fn slides_in_direction(&self, dx: i8, dy: i8) -> bool {
+        let diagonal = dx != 0 && dy != 0;
+        self.is_slider()
+            && match self {
+                PieceType::Bishop => diagonal,
+                PieceType::Rook => !diagonal,
+                _ => true,
+            }
+    }
 }
 
 impl From<&PromotionType> for PieceType {
@@ -33,3 +61,41 @@ impl From<&PromotionType> for PieceType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::PieceType;
+
+    #[test]
+    fn all_piece_types() {
+        for (index, piece_type) in PieceType::ALL.iter().enumerate() {
+            assert_eq!(*piece_type as usize, index);
+            assert_eq!(
+                PieceType::ALL.iter().filter(|p| *p == piece_type).count(),
+                1
+            );
+        }
+    }
+
+    #[test]
+    fn is_slider() {
+        assert!(!PieceType::Pawn.is_slider());
+        assert!(!PieceType::Knight.is_slider());
+        assert!(PieceType::Bishop.is_slider());
+        assert!(PieceType::Rook.is_slider());
+        assert!(PieceType::Queen.is_slider());
+        assert!(!PieceType::King.is_slider());
+    }
+
+    #[test]
+    fn slides_in_direction() {
+        assert!(PieceType::Bishop.slides_in_direction(1, 1));
+        assert!(!PieceType::Bishop.slides_in_direction(1, 0));
+        assert!(PieceType::Rook.slides_in_direction(0, -1));
+        assert!(!PieceType::Rook.slides_in_direction(-1, 1));
+        assert!(PieceType::Queen.slides_in_direction(1, 0));
+        assert!(PieceType::Queen.slides_in_direction(-1, -1));
+        assert!(!PieceType::King.slides_in_direction(1, 0));
+        assert!(!PieceType::Pawn.slides_in_direction(0, 1));
+    }
+}
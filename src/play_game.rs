@@ -1,5 +1,9 @@
 use crate::{
-    color::Color, game::Game, move_generation::MoveGenerator, moves::Move, players::PlayerInterface,
+    color::Color,
+    game::{Game, GameResult},
+    move_generation::MoveGenerator,
+    moves::Move,
+    players::PlayerInterface,
 };
 
 #[derive(Default)]
@@ -27,7 +31,8 @@ pub enum ConnectResult {
 }
 
 pub enum TurnResult {
-    Checkmate,
+    /// The game is over, the color won
+    Checkmate(Color),
     Stalemate,
     InProgress(Move, Color),
     PlayerNotMakingMoves,
@@ -126,14 +131,18 @@ impl PlayGame<Playing> {
             .all_legal_moves(current_color)
             .is_empty()
         {
-            println!("Checkmate!");
-            return TurnResult::Checkmate;
+            if !game.is_in_check() {
+                return TurnResult::Stalemate;
+            }
+            // The player who just moved gave mate
+            return TurnResult::Checkmate(current_color.opposite());
         }
 
         let mut try_counter = 10;
         loop {
             if let Some(mv) = player.make_move(game) {
                 self.inner.game.make_move(mv).expect("Failed to make move");
+                player.ponder(&self.inner.game);
                 break TurnResult::InProgress(mv, current_color);
             }
             try_counter -= 1;
@@ -144,7 +153,82 @@ impl PlayGame<Playing> {
         }
     }
 
+    /// Lets the players move until the game is over and returns the result.
+    /// Automatic draws (fivefold repetition, 75 moves) end the game as well.
+    /// Returns `InProgress` if a player stops making moves.
+    pub fn play_until_end(&mut self) -> GameResult {
+        loop {
+            match self.wait_for_move() {
+                TurnResult::InProgress(_, _) if self.inner.game.is_draw() => {
+                    return GameResult::Draw
+                }
+                TurnResult::InProgress(_, _) => {}
+                TurnResult::Checkmate(Color::White) => return GameResult::WhiteWins,
+                TurnResult::Checkmate(Color::Black) => return GameResult::BlackWins,
+                TurnResult::Stalemate => return GameResult::Draw,
+                TurnResult::PlayerNotMakingMoves => return GameResult::InProgress,
+            }
+        }
+    }
+
     pub fn game(&self) -> &Game {
         &self.inner.game
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fen::Fen, players::bot_random::BotRandom};
+
+    /// Plays the moves of a fixed line, one per ply
+    struct ScriptedPlayer(&'static [&'static str]);
+
+    impl PlayerInterface for ScriptedPlayer {
+        fn make_move(&self, game: &Game) -> Option<Move> {
+            let uci = self.0.get(game.move_stack().len())?;
+            game.resolve_move(&uci.parse().ok()?)
+        }
+    }
+
+    fn play(game: Game, line: &'static [&'static str]) -> TurnResult {
+        let mut playing = PlayGame::default()
+            .connect_player(Box::new(ScriptedPlayer(line)), Color::White)
+            .expect_waiting()
+            .connect_player(Box::new(ScriptedPlayer(line)), Color::Black)
+            .expect_ready()
+            .start(game);
+        loop {
+            match playing.wait_for_move() {
+                TurnResult::InProgress(_, _) => {}
+                result => return result,
+            }
+        }
+    }
+
+    #[test]
+    fn fools_mate_reports_winner() {
+        let result = play(Game::default(), &["f2f3", "e7e5", "g2g4", "d8h4"]);
+        assert!(matches!(result, TurnResult::Checkmate(Color::Black)));
+    }
+
+    #[test]
+    fn stalemate_is_no_checkmate() {
+        let game = Fen::parse_game("7k/8/6Q1/8/8/8/8/K7 w - - 0 1").unwrap();
+        let result = play(game, &["g6f7"]);
+        assert!(matches!(result, TurnResult::Stalemate));
+    }
+
+    #[test]
+    fn random_bots_play_until_the_end() {
+        let mut playing = PlayGame::default()
+            .connect_player(Box::new(BotRandom::seeded(1)), Color::White)
+            .expect_waiting()
+            .connect_player(Box::new(BotRandom::seeded(2)), Color::Black)
+            .expect_ready()
+            .start(Game::default());
+        let result = playing.play_until_end();
+        assert_ne!(result, GameResult::InProgress);
+        assert_eq!(result, playing.game().result());
+    }
+}
@@ -1,8 +1,15 @@
 use crate::{game::Game, moves::Move};
 
+pub mod bot_greedy;
+pub mod bot_minimax;
 pub mod bot_random;
 pub mod player_cli;
+pub mod ponder;
 
 pub trait PlayerInterface {
     fn make_move(&self, game: &Game) -> Option<Move>;
+
+    /// Called after the player moved, while the opponent is thinking.
+    /// Does nothing by default.
+    fn ponder(&self, _game: &Game) {}
 }
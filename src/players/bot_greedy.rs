@@ -0,0 +1,76 @@
+use crate::{game::Game, move_generation::MoveGenerator, moves::Move};
+
+use super::PlayerInterface;
+
+/// Takes the most valuable piece it can get right now and doesn't look any further.
+/// Captures of a defended piece worth less than the capturing piece are skipped.
+/// Without a good capture the first legal move is played, so the bot is deterministic.
+pub struct BotGreedy;
+
+impl PlayerInterface for BotGreedy {
+    fn make_move(&self, game: &Game) -> Option<Move> {
+        let moves = MoveGenerator::new(game).all_legal_moves(game.current_turn());
+        let mut best: Option<(Move, i32)> = None;
+        for mov in moves.iter() {
+            let gain = Self::capture_gain(game, mov);
+            if best.is_none_or(|(_, best_gain)| gain > best_gain) {
+                best = Some((*mov, gain));
+            }
+        }
+        best.map(|(mov, _)| mov)
+    }
+}
+
+impl BotGreedy {
+    /// Value of the captured piece, 0 for quiet moves.
+    /// Captures losing material score the material lost, so they are below quiet moves.
+    fn capture_gain(game: &Game, mov: &Move) -> i32 {
+        let Some(captured) = mov.move_type.capture_type() else {
+            return 0;
+        };
+        let Some(attacker) = game.board().piece_at(&mov.from) else {
+            return 0;
+        };
+        let defended = game
+            .bitboards()
+            .attacks(game.current_turn().opposite())
+            .contains(&mov.to);
+        if defended && captured.value() < attacker.piece_type().value() {
+            return -(attacker.piece_type().value() - captured.value());
+        }
+        captured.value()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fen::Fen, position::Position};
+
+    #[test]
+    fn takes_free_queen() {
+        // The rook can take the pawn or the queen
+        let game = Fen::parse_game("4k3/8/3q4/8/8/3R3p/8/4K3 w - - 0 1").unwrap();
+        let mov = BotGreedy.make_move(&game).unwrap();
+        assert_eq!((mov.from, mov.to), (Position::D3, Position::D6));
+    }
+
+    #[test]
+    fn skips_defended_pawn() {
+        // Taking the pawn loses the queen to the other pawn
+        let game = Fen::parse_game("4k3/8/8/2p5/3p4/8/3Q4/4K3 w - - 0 1").unwrap();
+        let mov = BotGreedy.make_move(&game).unwrap();
+        assert!(mov.move_type.capture_type().is_none());
+    }
+
+    #[test]
+    fn skips_losing_capture_generated_first() {
+        // Qxb2 is the first legal move, but the pawn on c3 takes the queen back
+        let game = Fen::parse_game("4k3/8/8/8/8/2p5/1p6/Q3K3 w - - 0 1").unwrap();
+        let first = MoveGenerator::new(&game).all_legal_moves(game.current_turn())[0];
+        assert_eq!((first.from, first.to), (Position::A1, Position::B2));
+
+        let mov = BotGreedy.make_move(&game).unwrap();
+        assert!(mov.move_type.capture_type().is_none());
+    }
+}
@@ -0,0 +1,103 @@
+use crate::{
+    game::Game,
+    move_generation::MoveGenerator,
+    moves::Move,
+    search::{eval, mated_score},
+};
+
+use super::PlayerInterface;
+
+/// Plain minimax bot without any pruning.
+/// It is way slower than the alpha beta search, but easy to follow.
+/// Both searches have to agree on the best score for the same depth, which makes it useful to check the alpha beta search.
+pub struct BotMinimax {
+    pub depth: u32,
+}
+
+impl PlayerInterface for BotMinimax {
+    fn make_move(&self, game: &Game) -> Option<Move> {
+        let mut game = game.clone();
+        self.best_move(&mut game).map(|(mov, _)| mov)
+    }
+}
+
+impl BotMinimax {
+    /// Returns the best move with its score seen from the side to move.
+    pub fn best_move(&self, game: &mut Game) -> Option<(Move, i32)> {
+        if self.depth == 0 {
+            return None;
+        }
+
+        let mut best: Option<(Move, i32)> = None;
+        for mov in MoveGenerator::new(game).all_legal_moves(game.current_turn()) {
+            if game.make_move(mov).is_err() {
+                continue;
+            }
+            let score = -Self::negamax(game, self.depth - 1, 1);
+            game.unmake_move();
+
+            // Keep the first move on equal scores, like the alpha beta search does
+            if best
+                .map(|(_, best_score)| score > best_score)
+                .unwrap_or(true)
+            {
+                best = Some((mov, score));
+            }
+        }
+        best
+    }
+
+    /// Negamax is minimax written from the view of the side to move.
+    /// The best score for us is the worst score for the opponent, so we just negate the score of every child.
+    fn negamax(game: &mut Game, depth: u32, ply: u32) -> i32 {
+        if depth == 0 {
+            return eval(game);
+        }
+
+        let legal_moves = MoveGenerator::new(game).all_legal_moves(game.current_turn());
+        if legal_moves.is_empty() {
+            // Checkmate or stalemate. Uses the same scores as the alpha beta search
+            return if game.is_in_check() {
+                mated_score(ply)
+            } else {
+                0
+            };
+        }
+
+        let mut best = i32::MIN;
+        for mov in legal_moves {
+            if game.make_move(mov).is_err() {
+                continue;
+            }
+            best = best.max(-Self::negamax(game, depth - 1, ply + 1));
+            game.unmake_move();
+        }
+        best
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fen::Fen, search::AlphaBetaSearch};
+
+    fn assert_same_score_as_alpha_beta(fen: &str, depth: u32) {
+        let mut game = Fen::parse_game(fen).unwrap();
+        let (_, minimax_score) = BotMinimax { depth }.best_move(&mut game).unwrap();
+
+        let mut search = AlphaBetaSearch::new(&mut game).with_quiescence(false);
+        let (_, alpha_beta_score) = search.run(depth)[0].unwrap();
+
+        assert_eq!(minimax_score, alpha_beta_score);
+    }
+
+    #[test]
+    fn same_score_as_alpha_beta() {
+        // Back rank mate
+        assert_same_score_as_alpha_beta("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3);
+        // Knight fork on king and queen
+        assert_same_score_as_alpha_beta("q3k3/8/8/1N6/8/8/8/4K3 w - - 0 1", 3);
+        // Hanging queen
+        assert_same_score_as_alpha_beta("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 3);
+    }
+}
@@ -1,14 +1,32 @@
+use std::cell::RefCell;
+
 use crate::{game::Game, move_generation::MoveGenerator, moves::Move};
 
 use super::PlayerInterface;
-use rand::seq::SliceRandom;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+
+/// Plays a random legal move.
+#[derive(Default)]
+pub struct BotRandom {
+    // Uses the thread rng without a seed
+    rng: Option<RefCell<StdRng>>,
+}
 
-pub struct BotRandom;
+impl BotRandom {
+    /// Picks the moves from a seeded rng, so the same game is played every time.
+    pub fn seeded(seed: u64) -> Self {
+        Self {
+            rng: Some(RefCell::new(StdRng::seed_from_u64(seed))),
+        }
+    }
+}
 
 impl PlayerInterface for BotRandom {
     fn make_move(&self, game: &Game) -> Option<Move> {
         let moves = MoveGenerator::new(game).all_legal_moves(game.current_turn());
-        let mut rng = rand::thread_rng();
-        moves.choose(&mut rng).cloned()
+        match &self.rng {
+            Some(rng) => moves.choose(&mut *rng.borrow_mut()).cloned(),
+            None => moves.choose(&mut rand::thread_rng()).cloned(),
+        }
     }
 }
@@ -0,0 +1,174 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::{game::Game, moves::Move, search::AlphaBetaSearch};
+
+use super::PlayerInterface;
+
+/// Searches like [crate::search::BotBasic], but keeps thinking while the opponent is on the move.
+/// The opponent is expected to play the reply of the principal variation. The position after
+/// that reply is searched in the background and the result is reused if the prediction hits.
+/// If the prediction misses, the background search is stopped and its result thrown away.
+pub struct PonderingBot {
+    depth: u32,
+    // The opponent reply from the principal variation of the last search
+    expected_reply: Mutex<Option<Move>>,
+    pondering: Mutex<Option<Ponder>>,
+}
+
+struct Ponder {
+    // Hash of the predicted position
+    hash: u64,
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<Option<SearchResult>>,
+}
+
+impl Ponder {
+    // Stops the background search and waits for the thread to finish
+    fn cancel(self) {
+        self.stop.store(true, Ordering::Relaxed);
+        let _ = self.handle.join();
+    }
+}
+
+/// The best move and the expected reply to it
+type SearchResult = (Move, Option<Move>);
+
+impl PonderingBot {
+    pub fn new(depth: u32) -> Self {
+        Self {
+            depth,
+            expected_reply: Mutex::new(None),
+            pondering: Mutex::new(None),
+        }
+    }
+
+    /// Returns the opponent move the bot expects after its last move.
+    pub fn predicted_reply(&self) -> Option<Move> {
+        *self.expected_reply.lock().expect("Poisoned expected reply")
+    }
+
+    fn search(game: &Game, depth: u32, stop: Arc<AtomicBool>) -> Option<SearchResult> {
+        let mut game = game.clone();
+        let mut search = AlphaBetaSearch::new(&mut game).with_stop_flag(stop);
+        let (best, _) = search.run_iterative(depth).into_iter().next().flatten()?;
+        Some((best, search.principal_variation().get(1).copied()))
+    }
+
+    // Takes the pondered result, if it was searched for this position
+    fn ponder_hit(&self, game: &Game) -> Option<SearchResult> {
+        let ponder = self.pondering.lock().expect("Poisoned ponder").take()?;
+        if ponder.hash != game.hash() {
+            ponder.cancel();
+            return None;
+        }
+        ponder.handle.join().ok().flatten()
+    }
+}
+
+impl PlayerInterface for PonderingBot {
+    fn make_move(&self, game: &Game) -> Option<Move> {
+        let (mov, reply) = self
+            .ponder_hit(game)
+            .or_else(|| Self::search(game, self.depth, Arc::default()))?;
+        *self.expected_reply.lock().expect("Poisoned expected reply") = reply;
+        Some(mov)
+    }
+
+    fn ponder(&self, game: &Game) {
+        let Some(reply) = self.predicted_reply() else {
+            return;
+        };
+        let mut predicted = game.clone();
+        if predicted.make_move(reply).is_err() {
+            return;
+        }
+        let mut pondering = self.pondering.lock().expect("Poisoned ponder");
+        if let Some(previous) = pondering.take() {
+            previous.cancel();
+        }
+        let hash = predicted.hash();
+        let depth = self.depth;
+        let stop = Arc::new(AtomicBool::new(false));
+        let search_stop = stop.clone();
+        let handle = thread::spawn(move || Self::search(&predicted, depth, search_stop));
+        *pondering = Some(Ponder { hash, stop, handle });
+    }
+}
+
+impl Drop for PonderingBot {
+    fn drop(&mut self) {
+        if let Some(ponder) = self.pondering.get_mut().ok().and_then(Option::take) {
+            ponder.cancel();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fen::Fen, moves::MoveType, position::Position};
+
+    #[test]
+    fn predicts_reply_of_principal_variation() {
+        // Knight fork on king and queen, the king has to move
+        let mut game = Fen::parse_game("q3k3/8/8/1N6/8/8/8/4K3 w - - 0 1").unwrap();
+        let bot = PonderingBot::new(3);
+        let mov = bot.make_move(&game).unwrap();
+
+        let mut search = AlphaBetaSearch::new(&mut game);
+        search.run_iterative(3);
+        let pv = search.principal_variation();
+        assert_eq!(mov, pv[0]);
+        assert_eq!(bot.predicted_reply(), Some(pv[1]));
+    }
+
+    #[test]
+    fn no_prediction_before_the_first_move() {
+        let bot = PonderingBot::new(2);
+        assert_eq!(bot.predicted_reply(), None);
+        // Nothing to ponder on, so no background search is started
+        bot.ponder(&Game::default());
+        assert!(bot.pondering.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn missed_prediction_stops_pondering() {
+        let expected = Move::new(
+            Position::E7,
+            Position::E5,
+            MoveType::DoublePawnPush(Position::E6),
+        );
+        let mut played = Game::default();
+        played
+            .make_move(Move::new(
+                Position::E2,
+                Position::E4,
+                MoveType::DoublePawnPush(Position::E3),
+            ))
+            .unwrap();
+        // Far too deep to finish, the search only ends when it is stopped
+        let bot = PonderingBot {
+            depth: 50,
+            expected_reply: Mutex::new(Some(expected)),
+            pondering: Mutex::new(None),
+        };
+        bot.ponder(&played);
+
+        let mut missed = played.clone();
+        missed
+            .make_move(Move::new(
+                Position::D7,
+                Position::D5,
+                MoveType::DoublePawnPush(Position::D6),
+            ))
+            .unwrap();
+        assert_eq!(bot.ponder_hit(&missed), None);
+        assert!(bot.pondering.lock().unwrap().is_none());
+    }
+}
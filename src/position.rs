@@ -1,10 +1,14 @@
-use std::{fmt::Display, str::FromStr};
+use std::{
+    fmt::Display,
+    ops::{Add, Sub},
+    str::FromStr,
+};
 
 use anyhow::Context;
 
 /// The position on the board
 /// Bottom left is (0, 0) or in chess terms 'A1'
-#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct Position {
     pub(crate) x: u8,
     pub(crate) y: u8,
@@ -59,6 +63,18 @@ impl Position {
         dy / dy.abs()
     }
 
+    /// Creates the position from its file (`a`-`h`) and rank (`1`-`8`) characters.
+    pub fn from_file_rank(file: char, rank: char) -> anyhow::Result<Self> {
+        let x = (file as u8)
+            .checked_sub(b'a')
+            .with_context(|| format!("Unknown column char {file}"))?;
+        let y = (rank as u8)
+            .checked_sub(b'1')
+            .with_context(|| format!("Unknown row char {rank}"))?;
+
+        Self::new(x, y).ok_or(anyhow::anyhow!("Position out of bounds"))
+    }
+
     pub fn file(&self) -> u8 {
         self.x
     }
@@ -140,6 +156,60 @@ impl Position {
     pub const H8: Self = Self { x: 7, y: 7 };
 }
 
+/// A step on the board, `dx` moves across files and `dy` across ranks, like in [Position::offset].
+/// North is towards rank 8.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Offset {
+    pub dx: i8,
+    pub dy: i8,
+}
+
+impl Offset {
+    pub const fn new(dx: i8, dy: i8) -> Self {
+        Self { dx, dy }
+    }
+
+    pub const NORTH: Self = Self::new(0, 1);
+    pub const SOUTH: Self = Self::new(0, -1);
+    pub const EAST: Self = Self::new(1, 0);
+    pub const WEST: Self = Self::new(-1, 0);
+    pub const NORTH_EAST: Self = Self::new(1, 1);
+    pub const NORTH_WEST: Self = Self::new(-1, 1);
+    pub const SOUTH_EAST: Self = Self::new(1, -1);
+    pub const SOUTH_WEST: Self = Self::new(-1, -1);
+}
+
+/// Moves the position by the offset. Returns `None` if it leaves the board.
+impl Add<Offset> for Position {
+    type Output = Option<Position>;
+
+    fn add(self, rhs: Offset) -> Self::Output {
+        self.offset(rhs.dx, rhs.dy)
+    }
+}
+
+/// Moves the position in the opposite direction of the offset. Returns `None` if it leaves the board.
+impl Sub<Offset> for Position {
+    type Output = Option<Position>;
+
+    fn sub(self, rhs: Offset) -> Self::Output {
+        self.offset(-rhs.dx, -rhs.dy)
+    }
+}
+
+/// Orders positions by their board index, so rank by rank starting at `a1`.
+impl Ord for Position {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.board_index().cmp(&other.board_index())
+    }
+}
+
+impl PartialOrd for Position {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl Display for Position {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let col_char = (b'a' + self.x) as char;
@@ -159,14 +229,23 @@ impl FromStr for Position {
         let col_char = chars.next().ok_or(anyhow::anyhow!("No column character"))?;
         let row_char = chars.next().ok_or(anyhow::anyhow!("No row character"))?;
 
-        let x = (col_char as u8)
-            .checked_sub(b'a')
-            .with_context(|| format!("Unknown column char {col_char}"))?;
-        let y = (row_char as u8)
-            .checked_sub(b'1')
-            .with_context(|| format!("Unknown row char {row_char}"))?;
+        Self::from_file_rank(col_char, row_char)
+    }
+}
 
-        Self::new(x, y).ok_or(anyhow::anyhow!("Position out of bounds"))
+/// The board index, see [Position::board_index]
+impl From<Position> for u8 {
+    fn from(value: Position) -> Self {
+        value.board_index() as u8
+    }
+}
+
+impl TryFrom<u8> for Position {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        Self::from_board_index(value as usize)
+            .with_context(|| format!("Board index {value} out of range"))
     }
 }
 
@@ -201,6 +280,29 @@ mod tests {
         assert_eq!(format!("{}", pos), "h8");
     }
 
+    #[test]
+    fn test_position_order() {
+        assert!(Position::A1 < Position::B1);
+        assert!(Position::B1 < Position::A2);
+        assert!(Position::A2 < Position::H8);
+        assert!(Position::H1 < Position::A2);
+
+        let mut positions = vec![Position::H8, Position::A2, Position::B1, Position::A1];
+        positions.sort();
+        assert_eq!(
+            positions,
+            vec![Position::A1, Position::B1, Position::A2, Position::H8]
+        );
+    }
+
+    #[test]
+    fn test_from_file_rank() {
+        assert_eq!(Position::from_file_rank('a', '1').unwrap(), Position::A1);
+        assert_eq!(Position::from_file_rank('h', '8').unwrap(), Position::H8);
+        assert!(Position::from_file_rank('i', '9').is_err());
+        assert!(Position::from_file_rank('A', '1').is_err());
+    }
+
     #[test]
     fn test_position_from_str() {
         let pos = Position::from_str("a1");
@@ -219,6 +321,20 @@ mod tests {
         assert!(pos.is_err());
     }
 
+    #[test]
+    fn test_offset() {
+        assert_eq!(Position::A1 + Offset::NORTH, Some(Position::A2));
+        assert_eq!(Position::A1 + Offset::NORTH_EAST, Some(Position::B2));
+        assert_eq!(Position::E4 + Offset::new(1, 2), Some(Position::F6));
+        assert_eq!(Position::A2 - Offset::NORTH, Some(Position::A1));
+
+        // Leaving the board
+        assert_eq!(Position::A1 + Offset::WEST, None);
+        assert_eq!(Position::A1 + Offset::SOUTH, None);
+        assert_eq!(Position::H8 + Offset::NORTH_EAST, None);
+        assert_eq!(Position::H1 - Offset::WEST, None);
+    }
+
     #[test]
     fn test_direction() {
         let pos1 = Position::new_unchecked(0, 0);
@@ -231,4 +347,16 @@ mod tests {
         assert_eq!(pos1.rank_direction(&pos2), -1);
         assert_eq!(pos1.file_direction(&pos2), -1);
     }
+
+    #[test]
+    fn test_index_conversions() {
+        for index in 0..64u8 {
+            let position = Position::try_from(index).unwrap();
+            assert_eq!(u8::from(position), index);
+            assert_eq!(position.to_string().parse::<Position>().unwrap(), position);
+        }
+        assert_eq!(u8::from(Position::H8), 63);
+        assert!(Position::try_from(64).is_err());
+        assert!(Position::try_from(u8::MAX).is_err());
+    }
 }
@@ -2,6 +2,11 @@ use crate::{game::Game, position::Position};
 
 pub trait BoardPrinter {
     fn get_char(&self, position: Position, game: &Game) -> char;
+
+    /// The characters written left and right of the square, spaces unless the square is marked.
+    fn brackets(&self, _position: Position, _game: &Game) -> (char, char) {
+        (' ', ' ')
+    }
 }
 
 impl<F> BoardPrinter for F
@@ -36,3 +41,63 @@ impl BoardPrinter for DefaultBoardPrinter {
         }
     }
 }
+
+/// Wraps another printer and brackets the from and to square of the last move, e.g. `[P]`.
+/// Without a last move the board is printed like the wrapped printer does.
+pub struct LastMovePrinter<P> {
+    base: P,
+}
+
+impl<P: BoardPrinter> LastMovePrinter<P> {
+    pub fn new(base: P) -> Self {
+        Self { base }
+    }
+}
+
+impl<P: BoardPrinter> BoardPrinter for LastMovePrinter<P> {
+    fn get_char(&self, position: Position, game: &Game) -> char {
+        self.base.get_char(position, game)
+    }
+
+    fn brackets(&self, position: Position, game: &Game) -> (char, char) {
+        match game.last_move() {
+            Some(mov) if mov.from == position || mov.to == position => ('[', ']'),
+            _ => self.base.brackets(position, game),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{DefaultBoardPrinter, LastMovePrinter};
+    use crate::{
+        game::Game,
+        moves::{Move, MoveType},
+        position::Position,
+    };
+
+    fn render(game: &Game) -> Vec<String> {
+        let mut out = Vec::new();
+        game.render_custom(LastMovePrinter::new(DefaultBoardPrinter), &mut out)
+            .unwrap();
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn last_move_is_bracketed() {
+        let mut game = Game::from_str("4k3/8/8/8/8/8/8/R3K3 w Q - 0 1").unwrap();
+        assert_eq!(render(&game)[15], "| R |   |   |   | K |   |   |   | 1");
+
+        game.make_move(Move::new(Position::A1, Position::A5, MoveType::Quiet))
+            .unwrap();
+        let lines = render(&game);
+        assert_eq!(lines[7], "|[R]|   |   |   |   |   |   |   | 5");
+        assert_eq!(lines[15], "|[ ]|   |   |   | K |   |   |   | 1");
+    }
+}
@@ -0,0 +1,260 @@
+use anyhow::Context;
+
+use crate::{
+    color::Color,
+    game::Game,
+    move_generation::MoveGenerator,
+    moves::{Move, MoveType},
+    piece::Piece,
+    piece_type::PieceType,
+};
+
+/// Standard algebraic notation (e.g. `Nf3`, `exd5`, `O-O`, `e8=Q+`)
+pub struct San;
+
+impl San {
+    /// Returns the san of a legal move in the given game.
+    pub fn from_move(game: &Game, mov: &Move) -> anyhow::Result<String> {
+        let legal_moves = MoveGenerator::new(game).all_legal_moves(game.current_turn());
+        if !legal_moves.contains(mov) {
+            anyhow::bail!("Move {mov} is not legal in this position");
+        }
+
+        let mut san = Self::without_check(game, mov, &legal_moves)?;
+
+        let mut after_move = game.clone();
+        after_move.make_move(*mov)?;
+        if after_move.is_in_check() {
+            let is_mate = MoveGenerator::new(&after_move)
+                .all_legal_moves(after_move.current_turn())
+                .is_empty();
+            san.push(if is_mate { '#' } else { '+' });
+        }
+        Ok(san)
+    }
+
+    /// Parses the san in the given game and returns the matching legal move.
+    /// A promotion without a given piece (e.g. `e8`) is parsed as queen promotion.
+    pub fn parse_move(game: &Game, san: &str) -> anyhow::Result<Move> {
+        let trimmed = san
+            .trim()
+            .trim_end_matches(['+', '#', '!', '?'])
+            .replace('0', "O");
+
+        let legal_moves = MoveGenerator::new(game).all_legal_moves(game.current_turn());
+        let find_move = |san: &str| -> anyhow::Result<Option<Move>> {
+            for mov in legal_moves.iter() {
+                if Self::without_check(game, mov, &legal_moves)? == san {
+                    return Ok(Some(*mov));
+                }
+            }
+            Ok(None)
+        };
+
+        if let Some(mov) = find_move(&trimmed)? {
+            return Ok(mov);
+        }
+        if !trimmed.contains('=') {
+            if let Some(mov) = find_move(&format!("{trimmed}=Q"))? {
+                return Ok(mov);
+            }
+        }
+
+        anyhow::bail!("No legal move found for san '{san}'")
+    }
+
+    fn without_check(game: &Game, mov: &Move, legal_moves: &[Move]) -> anyhow::Result<String> {
+        if let MoveType::Castle = mov.move_type {
+            return Ok(if mov.to.file() > mov.from.file() {
+                "O-O".to_string()
+            } else {
+                "O-O-O".to_string()
+            });
+        }
+
+        let piece = game
+            .board()
+            .piece_at(&mov.from)
+            .with_context(|| format!("No piece to move at position {}", mov.from))?;
+
+        let mut san = String::new();
+        if piece.piece_type() == PieceType::Pawn {
+            if mov.move_type.is_capture() {
+                san.push(file_char(mov.from.file()));
+            }
+        } else {
+            san.push(Piece::new(piece.piece_type(), Color::White).get_print_char());
+
+            // Other pieces of the same type, that can move to the same square
+            let ambiguous = legal_moves
+                .iter()
+                .filter(|other| other.to == mov.to && other.from != mov.from)
+                .filter(|other| {
+                    game.board()
+                        .piece_at(&other.from)
+                        .is_some_and(|p| p.piece_type() == piece.piece_type())
+                })
+                .collect::<Vec<_>>();
+            if !ambiguous.is_empty() {
+                if ambiguous.iter().all(|o| o.from.file() != mov.from.file()) {
+                    san.push(file_char(mov.from.file()));
+                } else if ambiguous.iter().all(|o| o.from.rank() != mov.from.rank()) {
+                    san.push(rank_char(mov.from.rank()));
+                } else {
+                    san.push(file_char(mov.from.file()));
+                    san.push(rank_char(mov.from.rank()));
+                }
+            }
+        }
+
+        if mov.move_type.is_capture() {
+            san.push('x');
+        }
+        san.push_str(&mov.to.to_string());
+
+        if let MoveType::PromotionQuite(promotion) | MoveType::PromotionCapture(promotion, _) =
+            mov.move_type
+        {
+            san.push('=');
+            san.push_str(&promotion.to_string().to_uppercase());
+        }
+
+        Ok(san)
+    }
+}
+
+fn file_char(file: u8) -> char {
+    (b'a' + file) as char
+}
+
+fn rank_char(rank: u8) -> char {
+    (b'1' + rank) as char
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{fen::Fen, moves::PromotionType, position::Position};
+
+    fn assert_san(fen: &str, mov: Move, expected: &str) {
+        let game = Fen::parse_game(fen).unwrap();
+        assert_eq!(San::from_move(&game, &mov).unwrap(), expected);
+        assert_eq!(San::parse_move(&game, expected).unwrap(), mov);
+    }
+
+    #[test]
+    fn pawn_moves() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_san(
+            start,
+            Move::new(
+                Position::E2,
+                Position::E4,
+                MoveType::DoublePawnPush(Position::E3),
+            ),
+            "e4",
+        );
+        assert_san(
+            "4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1",
+            Move::new(
+                Position::E4,
+                Position::D5,
+                MoveType::Capture(PieceType::Pawn),
+            ),
+            "exd5",
+        );
+    }
+
+    #[test]
+    fn piece_moves_and_disambiguation() {
+        let start = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+        assert_san(
+            start,
+            Move::new(Position::G1, Position::F3, MoveType::Quiet),
+            "Nf3",
+        );
+        // Both knights can go to d2
+        assert_san(
+            "4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1",
+            Move::new(Position::B1, Position::D2, MoveType::Quiet),
+            "Nbd2",
+        );
+        // Both rooks on the same file
+        assert_san(
+            "4k3/R7/8/8/8/8/8/R3K3 w - - 0 1",
+            Move::new(Position::A1, Position::A4, MoveType::Quiet),
+            "R1a4",
+        );
+    }
+
+    #[test]
+    fn castle_promotion_and_check() {
+        assert_san(
+            "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1",
+            Move::new(Position::E1, Position::G1, MoveType::Castle),
+            "O-O",
+        );
+        assert_san(
+            "r3k2r/8/8/8/8/8/8/R3K2R b KQkq - 0 1",
+            Move::new(Position::E8, Position::C8, MoveType::Castle),
+            "O-O-O",
+        );
+        assert_san(
+            "3k4/6P1/8/8/8/8/8/4K3 w - - 0 1",
+            Move::new(
+                Position::G7,
+                Position::G8,
+                MoveType::PromotionQuite(PromotionType::Queen),
+            ),
+            "g8=Q+",
+        );
+        // Back rank mate
+        assert_san(
+            "6k1/5ppp/8/8/8/8/8/R3K3 w - - 0 1",
+            Move::new(Position::A1, Position::A8, MoveType::Quiet),
+            "Ra8#",
+        );
+    }
+
+    #[test]
+    fn parse_promotion() {
+        let game = Fen::parse_game("3k4/6P1/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        let promote = |promotion| {
+            Move::new(
+                Position::G7,
+                Position::G8,
+                MoveType::PromotionQuite(promotion),
+            )
+        };
+        // Without a piece we promote to a queen
+        assert_eq!(
+            San::parse_move(&game, "g8").unwrap(),
+            promote(PromotionType::Queen)
+        );
+        assert_eq!(
+            San::parse_move(&game, "g8+").unwrap(),
+            promote(PromotionType::Queen)
+        );
+        assert_eq!(
+            San::parse_move(&game, "g8=R").unwrap(),
+            promote(PromotionType::Rook)
+        );
+        assert_eq!(
+            San::parse_move(&game, "g8=N").unwrap(),
+            promote(PromotionType::Knight)
+        );
+    }
+
+    #[test]
+    fn parse_invalid() {
+        let game = Game::default();
+        assert!(San::parse_move(&game, "Nf6").is_err());
+        assert!(San::parse_move(&game, "hello").is_err());
+        // Castle notation with zeros
+        let game = Fen::parse_game("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        assert_eq!(
+            San::parse_move(&game, "0-0").unwrap(),
+            Move::new(Position::E1, Position::G1, MoveType::Castle)
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use std::{cell::RefCell, collections::HashMap, time::Duration};
+
 pub struct ScopedTimer {
     name: &'static str,
     start: std::time::Instant,
@@ -18,3 +20,108 @@ impl Drop for ScopedTimer {
         println!("Timer '{}' elapsed: {}ml", self.name, elapsed.as_millis());
     }
 }
+
+thread_local! {
+    static REGISTRY: RefCell<TimerRegistry> = RefCell::new(TimerRegistry::default());
+}
+
+/// Accumulates durations by name for the current thread.
+/// Use [AggregatedTimer] to record scopes and [TimerRegistry::summary] to read the results.
+#[derive(Default, Debug)]
+pub struct TimerRegistry {
+    timings: HashMap<&'static str, (Duration, u32)>,
+}
+
+impl TimerRegistry {
+    pub fn record(name: &'static str, duration: Duration) {
+        REGISTRY.with(|registry| {
+            let mut registry = registry.borrow_mut();
+            let entry = registry.timings.entry(name).or_default();
+            entry.0 += duration;
+            entry.1 += 1;
+        });
+    }
+
+    /// Returns the total duration and the number of calls recorded for the given name
+    pub fn total(name: &'static str) -> Option<(Duration, u32)> {
+        REGISTRY.with(|registry| registry.borrow().timings.get(name).copied())
+    }
+
+    pub fn reset() {
+        REGISTRY.with(|registry| registry.borrow_mut().timings.clear());
+    }
+
+    /// Returns one line per timer, sorted by the total time spent (longest first)
+    pub fn summary() -> String {
+        REGISTRY.with(|registry| {
+            let registry = registry.borrow();
+            let mut timings = registry.timings.iter().collect::<Vec<_>>();
+            timings.sort_by_key(|(_, (duration, _))| std::cmp::Reverse(*duration));
+            timings
+                .into_iter()
+                .map(|(name, (duration, calls))| {
+                    format!("{name}: {}ml ({calls} calls)", duration.as_millis())
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+    }
+
+    pub fn print_summary() {
+        println!("{}", Self::summary());
+    }
+}
+
+/// Like [ScopedTimer], but adds the elapsed time to the [TimerRegistry] instead of printing it.
+pub struct AggregatedTimer {
+    name: &'static str,
+    start: std::time::Instant,
+}
+
+impl AggregatedTimer {
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            start: std::time::Instant::now(),
+        }
+    }
+}
+
+impl Drop for AggregatedTimer {
+    fn drop(&mut self) {
+        TimerRegistry::record(self.name, self.start.elapsed());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registry_accumulates() {
+        TimerRegistry::reset();
+        for _ in 0..2 {
+            let _t = AggregatedTimer::new("first");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+        {
+            let _t = AggregatedTimer::new("second");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        let (first, first_calls) = TimerRegistry::total("first").unwrap();
+        let (second, second_calls) = TimerRegistry::total("second").unwrap();
+        assert!(first > Duration::ZERO);
+        assert!(second > Duration::ZERO);
+        assert_eq!(first_calls, 2);
+        assert_eq!(second_calls, 1);
+        assert!(TimerRegistry::total("unknown").is_none());
+
+        let summary = TimerRegistry::summary();
+        assert!(summary.contains("first"));
+        assert!(summary.contains("second"));
+
+        TimerRegistry::reset();
+        assert!(TimerRegistry::total("first").is_none());
+    }
+}
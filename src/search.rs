@@ -1,56 +1,485 @@
-use std::array;
+use std::{
+    array,
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+use rand::Rng;
 
+#[cfg(feature = "timers")]
+use crate::scoped_timer::{AggregatedTimer, ScopedTimer, TimerRegistry};
 use crate::{
+    color::Color,
+    epd::Epd,
     game::Game,
-    move_generation::MoveGenerator,
+    move_generation::{MoveCache, MoveGenerator},
     moves::{Move, MoveType},
     players::PlayerInterface,
-    scoped_timer::ScopedTimer,
+    san::San,
 };
 
 const MAX_DEPTH: u32 = 4;
 const MAX_MOVES: usize = 4;
+/// Default number of captures the quiescence search follows after the main search
+const QUIESCENCE_DEPTH: u32 = 8;
+/// The clock is only read every this many positions, as reading it is slow compared to a node
+const TIME_CHECK_INTERVAL: u32 = 256;
+/// Moves scoring at most this much below the best move can be picked in the opening
+const OPENING_MARGIN: i32 = 20;
+/// Score for being checkmated in the current position.
+/// Mates further away are scored closer to zero, so the fastest mate is preferred.
+pub const MATE_SCORE: i32 = 50000;
+/// Scores this close to the mate score are mate scores
+const MAX_MATE_PLY: i32 = 1000;
+
+/// Returns the score of the side that is checkmated after the given number of plies.
+pub fn mated_score(ply: u32) -> i32 {
+    -MATE_SCORE + ply as i32
+}
+
+/// Returns the number of moves until mate, if the score is a mate score.
+/// Positive if the side to move mates, negative if it gets mated.
+pub fn score_to_mate_distance(score: i32) -> Option<i32> {
+    if score.abs() < MATE_SCORE - MAX_MATE_PLY {
+        return None;
+    }
+    let moves = (MATE_SCORE - score.abs() + 1) / 2;
+    Some(if score > 0 { moves } else { -moves })
+}
+
+/// Formats the search result as uci `info` line (e.g. `info depth 4 score mate 2 pv h1g1 a7a8`).
+pub fn uci_info(depth: u32, score: i32, pv: &[Move]) -> String {
+    let score = match score_to_mate_distance(score) {
+        Some(moves) => format!("mate {moves}"),
+        None => format!("cp {score}"),
+    };
+    let pv = pv.iter().map(Move::to_uci).collect::<Vec<_>>().join(" ");
+    format!("info depth {depth} score {score} pv {pv}")
+}
+
+pub struct BotBasic {
+    opening_randomness: u8,
+    max_depth: u32,
+    time_limit: Option<Duration>,
+}
+
+impl Default for BotBasic {
+    fn default() -> Self {
+        Self {
+            opening_randomness: 0,
+            max_depth: MAX_DEPTH,
+            time_limit: None,
+        }
+    }
+}
+
+impl BotBasic {
+    /// For the given number of plies from the start, a random move is picked out of the moves
+    /// scoring close to the best move. Disabled by default.
+    pub fn with_opening_randomness(mut self, plies: u8) -> Self {
+        self.opening_randomness = plies;
+        self
+    }
+
+    /// The deepest iteration searched for a move (4 by default).
+    pub fn with_max_depth(mut self, depth: u32) -> Self {
+        self.max_depth = depth.max(1);
+        self
+    }
+
+    /// Stops searching after this time, the best move of the last finished iteration is played.
+    pub fn with_time_limit(mut self, time: Duration) -> Self {
+        self.time_limit = Some(time);
+        self
+    }
+
+    /// Searches the game like [PlayerInterface::make_move] does.
+    /// Returns the best moves, best first, and the stats of the search.
+    pub fn search(&self, game: &Game) -> (Vec<Option<(Move, i32)>>, SearchStats) {
+        let mut search_game = game.clone();
+        let mut search = AlphaBetaSearch::new(&mut search_game);
+        if game.move_stack().len() < self.opening_randomness as usize {
+            // Close moves need an exact score, not just the bound from the cutoff
+            search = search.with_root_margin(OPENING_MARGIN + 1);
+        }
+        if let Some(time) = self.time_limit {
+            search = search.with_time_limit(time);
+        }
+        let best_moves = search.run_iterative(self.max_depth);
+        (best_moves, search.stats().clone())
+    }
+
+    fn pick_move(
+        &self,
+        game: &Game,
+        best_moves: &[Option<(Move, i32)>],
+        rng: &mut impl Rng,
+    ) -> Option<Move> {
+        let (best, best_score) = best_moves.first().copied().flatten()?;
+        if game.move_stack().len() >= self.opening_randomness as usize {
+            return Some(best);
+        }
 
-pub struct BotBasic;
+        let candidates = best_moves
+            .iter()
+            .flatten()
+            .filter(|(_, score)| best_score - score <= OPENING_MARGIN)
+            .collect::<Vec<_>>();
+        Some(candidates[rng.gen_range(0..candidates.len())].0)
+    }
+}
 
 impl PlayerInterface for BotBasic {
     fn make_move(&self, game: &Game) -> Option<Move> {
-        let mut game = game.clone();
-        best_move(&mut game).map(|(mov, _)| mov)
+        let (best_moves, stats) = self.search(game);
+        println!("{stats}");
+        self.pick_move(game, &best_moves, &mut rand::thread_rng())
     }
 }
 
 pub fn best_moves(game: &mut Game) -> Vec<Option<(Move, i32)>> {
+    best_moves_with_depth(game, MAX_DEPTH)
+}
+
+pub fn best_moves_with_depth(game: &mut Game, depth: u32) -> Vec<Option<(Move, i32)>> {
     let mut search = AlphaBetaSearch::new(game);
-    {
-        let _t = ScopedTimer::new("search");
-        search.search(MAX_DEPTH, -100000, 100000, true);
+    let best_moves = search.run(depth);
+    if let Some((_, score)) = best_moves[0] {
+        println!("{}", uci_info(depth, score, search.principal_variation()));
     }
-    println!("Looked at {} positions", search.looked_at_positions);
-    println!("Skipped {} positions", search.skipped_positions);
+    println!("{}", search.stats());
 
-    search.best_moves.to_vec()
+    best_moves
 }
 
 pub fn best_move(game: &mut Game) -> Option<(Move, i32)> {
     best_moves(game).into_iter().next().flatten()
 }
 
-struct AlphaBetaSearch<'a> {
+/// Evaluates the position by searching it to the given depth.
+/// The score is seen from the side to move.
+pub fn evaluate_with_depth(game: &mut Game, depth: u32) -> i32 {
+    if depth == 0 {
+        return eval(game);
+    }
+    let mut search = AlphaBetaSearch::new(game);
+    match search.run(depth).into_iter().next().flatten() {
+        Some((_, score)) => score,
+        // No legal moves, same scores as in the search
+        None if game.is_in_check() => mated_score(0),
+        None => 0,
+    }
+}
+
+/// Evaluates the position by searching it to the given depth.
+/// Unlike [evaluate_with_depth] the score is in centipawns seen from white,
+/// positive scores are good for white no matter whose turn it is.
+pub fn evaluate_position(game: &mut Game, depth: u32) -> i32 {
+    evaluate_with_depth(game, depth) * game.current_turn().sign()
+}
+
+/// Searches the position of the epd and checks if the best move is one of its `bm` moves.
+/// Returns false if the epd or one of its moves can't be parsed, or it has no `bm` operation.
+pub fn solve_epd(epd: &str, depth: u32) -> bool {
+    let Ok((mut game, operations)) = Epd::parse(epd) else {
+        return false;
+    };
+    let Some(best_moves) = operations.get("bm") else {
+        return false;
+    };
+    let Ok(expected) = best_moves
+        .split_whitespace()
+        .map(|san| San::parse_move(&game, san))
+        .collect::<anyhow::Result<Vec<_>>>()
+    else {
+        return false;
+    };
+    let mut search = AlphaBetaSearch::new(&mut game);
+    search.run_iterative(depth)[0].is_some_and(|(mov, _)| expected.contains(&mov))
+}
+
+/// Counters collected during a search, to judge the move ordering.
+#[derive(Debug, Clone, Default)]
+pub struct SearchStats {
+    pub depth: u32,
+    pub looked_at_positions: u32,
+    /// Beta cutoffs, the remaining moves of the position are skipped
+    pub cutoffs: u32,
+    /// Cutoffs caused by the first move tried
+    pub first_move_cutoffs: u32,
+    /// Deepest ply reached, including the quiescence search
+    pub seldepth: u32,
+}
+
+impl SearchStats {
+    /// Fraction of the cutoffs that happened on the first move, 0 without cutoffs.
+    /// The closer to 1, the better the move ordering.
+    pub fn first_move_cutoff_rate(&self) -> f64 {
+        if self.cutoffs == 0 {
+            return 0.0;
+        }
+        self.first_move_cutoffs as f64 / self.cutoffs as f64
+    }
+
+    /// Average number of moves looked at per ply (`nodes^(1/depth)`).
+    pub fn effective_branching_factor(&self) -> f64 {
+        if self.depth == 0 {
+            return 0.0;
+        }
+        (self.looked_at_positions as f64).powf(1.0 / self.depth as f64)
+    }
+
+    fn count_cutoff(&mut self, move_index: usize) {
+        self.cutoffs += 1;
+        if move_index == 0 {
+            self.first_move_cutoffs += 1;
+        }
+    }
+}
+
+impl std::fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Looked at {} positions", self.looked_at_positions)?;
+        writeln!(
+            f,
+            "Cutoffs: {} ({:.1}% on the first move)",
+            self.cutoffs,
+            self.first_move_cutoff_rate() * 100.0
+        )?;
+        write!(
+            f,
+            "Effective branching factor: {:.2}",
+            self.effective_branching_factor()
+        )
+    }
+}
+
+pub struct AlphaBetaSearch<'a> {
     game: &'a mut Game,
     best_moves: [Option<(Move, i32)>; MAX_MOVES],
-    looked_at_positions: u32,
-    skipped_positions: u32,
+    stats: SearchStats,
+    quiescence: bool,
+    quiescence_depth: u32,
+    pvs: bool,
+    root_margin: i32,
+    root_depth: u32,
+    contempt: i32,
+    // The side to move at the root, draws are scored against it with the contempt
+    root_color: Color,
+    // The best line found from every ply on. The first one is the principal variation
+    pv_table: Vec<Vec<Move>>,
+    // Hashes of the positions played in the game before the search started
+    history: Vec<u64>,
+    // Hashes of the positions on the currently searched line, starting with the root
+    path: Vec<u64>,
+    // Best or cutoff move found per position, tried first when the position is searched again.
+    // Kept between the iterations of iterative deepening
+    hash_moves: HashMap<u64, Move>,
+    // One move list per ply, filled by the move generation, so the lists are reused between the nodes
+    move_buffers: Vec<Vec<Move>>,
+    move_cache: Option<MoveCache>,
+    node_limit: Option<u64>,
+    time_limit: Option<Duration>,
+    // When the current run has to stop, set from the time limit when the run starts
+    deadline: Option<Instant>,
+    // Set from another thread to stop the search
+    stop_flag: Option<Arc<AtomicBool>>,
+    // Set once the node or time limit is reached, every node returns right away after that
+    stopped: bool,
+    // Whether an earlier iteration completed. Until then the search only stops once a root move is searched
+    completed_iteration: bool,
 }
 
 impl<'a> AlphaBetaSearch<'a> {
     pub fn new(game: &'a mut Game) -> Self {
+        let history = game.position_hashes();
         Self {
             game,
             best_moves: array::from_fn(|_| None),
-            looked_at_positions: 0,
-            skipped_positions: 0,
+            stats: SearchStats::default(),
+            quiescence: true,
+            quiescence_depth: QUIESCENCE_DEPTH,
+            pvs: true,
+            root_margin: 1,
+            root_depth: 0,
+            contempt: 0,
+            root_color: Color::White,
+            pv_table: Vec::new(),
+            history,
+            path: Vec::new(),
+            hash_moves: HashMap::new(),
+            move_buffers: Vec::new(),
+            move_cache: None,
+            node_limit: None,
+            time_limit: None,
+            deadline: None,
+            stop_flag: None,
+            stopped: false,
+            completed_iteration: false,
+        }
+    }
+
+    /// Enables or disables the quiescence search at the leaf nodes (enabled by default).
+    /// Disabling it runs a pure fixed depth alpha beta search, which helps to isolate bugs.
+    pub fn with_quiescence(mut self, quiescence: bool) -> Self {
+        self.quiescence = quiescence;
+        self
+    }
+
+    /// Limits the number of captures the quiescence search follows (8 by default).
+    /// Keeps long capture chains from making the search explode.
+    pub fn with_quiescence_depth(mut self, depth: u32) -> Self {
+        self.quiescence_depth = depth;
+        self
+    }
+
+    /// Enables or disables the principal variation search (enabled by default).
+    /// Below the root only the first move gets the full window. The others are searched with a null window
+    /// proving they are not better, and only searched again with the full window if they are.
+    pub fn with_pvs(mut self, pvs: bool) -> Self {
+        self.pvs = pvs;
+        self
+    }
+
+    /// Moves at the root scoring less than this below the best move get an exact score (1 by default).
+    /// Worse moves are cut off and only get an upper bound as score.
+    pub fn with_root_margin(mut self, margin: i32) -> Self {
+        self.root_margin = margin.max(1);
+        self
+    }
+
+    /// Scores draws this much below zero for the side to move at the root (0 by default).
+    /// A positive contempt makes the engine play on instead of accepting a draw by repetition or stalemate.
+    pub fn with_contempt(mut self, contempt: i32) -> Self {
+        self.contempt = contempt;
+        self
+    }
+
+    /// Caches the legal moves of the last `capacity` positions, so transpositions skip the move generation.
+    /// The cache is kept between runs.
+    pub fn with_move_cache(mut self, capacity: usize) -> Self {
+        self.move_cache = Some(MoveCache::new(capacity));
+        self
+    }
+
+    /// Stops the search once this many positions were looked at, like `go nodes` in uci.
+    /// The best moves of the last completed iteration are returned, or the moves searched so far.
+    pub fn with_node_limit(mut self, nodes: u64) -> Self {
+        self.node_limit = Some(nodes);
+        self
+    }
+
+    /// Stops the search once it ran this long, like `go movetime` in uci.
+    /// Like with the node limit, at least one root move is searched.
+    pub fn with_time_limit(mut self, time: Duration) -> Self {
+        self.time_limit = Some(time);
+        self
+    }
+
+    /// Stops the search once the flag is set, like `stop` in uci. The flag can be set from another thread.
+    /// Like with the node limit, at least one root move is searched.
+    pub fn with_stop_flag(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop_flag = Some(stop);
+        self
+    }
+
+    /// Searches the game up to the given depth and returns the best moves found, best first.
+    pub fn run(&mut self, depth: u32) -> Vec<Option<(Move, i32)>> {
+        #[cfg(feature = "timers")]
+        TimerRegistry::reset();
+        self.stats = SearchStats::default();
+        self.stopped = false;
+        self.completed_iteration = false;
+        self.deadline = self.time_limit.map(|time| Instant::now() + time);
+        self.hash_moves.clear();
+        self.search_depth(depth)
+    }
+
+    /// Searches depth 1, 2, ... up to the given depth, reusing the best moves of the previous
+    /// iteration for the move ordering. The stats count the positions of all iterations.
+    pub fn run_iterative(&mut self, depth: u32) -> Vec<Option<(Move, i32)>> {
+        #[cfg(feature = "timers")]
+        TimerRegistry::reset();
+        self.stats = SearchStats::default();
+        self.stopped = false;
+        self.completed_iteration = false;
+        self.deadline = self.time_limit.map(|time| Instant::now() + time);
+        self.hash_moves.clear();
+        let mut best_moves = Vec::new();
+        for depth in 1..=depth {
+            let iteration = self.search_depth(depth);
+            // The previous best move is searched first, so the moves of a stopped iteration are used
+            // as soon as it finished one
+            if !self.stopped || iteration[0].is_some() {
+                best_moves = iteration;
+            }
+            if self.stopped {
+                break;
+            }
+            self.completed_iteration = true;
+        }
+        best_moves
+    }
+
+    /// Whether the last run stopped early because of the node or time limit or the stop flag.
+    pub fn stopped(&self) -> bool {
+        self.stopped
+    }
+
+    fn reached_limit(&mut self) -> bool {
+        let has_move = self.completed_iteration || self.best_moves[0].is_some();
+        if self.stopped || !has_move {
+            return self.stopped;
+        }
+        if let Some(limit) = self.node_limit {
+            self.stopped |= self.stats.looked_at_positions as u64 >= limit;
         }
+        if let Some(stop) = &self.stop_flag {
+            self.stopped |= stop.load(Ordering::Relaxed);
+        }
+        if let Some(deadline) = self.deadline {
+            self.stopped |= self
+                .stats
+                .looked_at_positions
+                .is_multiple_of(TIME_CHECK_INTERVAL)
+                && Instant::now() >= deadline;
+        }
+        self.stopped
+    }
+
+    fn search_depth(&mut self, depth: u32) -> Vec<Option<(Move, i32)>> {
+        self.root_depth = depth;
+        self.root_color = self.game.current_turn();
+        self.stats.depth = depth;
+        self.best_moves = array::from_fn(|_| None);
+        self.pv_table = vec![Vec::new(); depth as usize + 1];
+        if self.move_buffers.len() <= depth as usize {
+            self.move_buffers.resize(depth as usize + 1, Vec::new());
+        }
+        self.path = vec![self.game.hash()];
+        {
+            #[cfg(feature = "timers")]
+            let _t = ScopedTimer::new("search");
+            self.search(depth, -100000, 100000, true);
+        }
+        self.best_moves.to_vec()
+    }
+
+    /// Returns the line of moves expected for both sides, starting with the best move.
+    pub fn principal_variation(&self) -> &[Move] {
+        self.pv_table.first().map(Vec::as_slice).unwrap_or_default()
+    }
+
+    pub fn stats(&self) -> &SearchStats {
+        &self.stats
+    }
+
+    pub fn move_cache(&self) -> Option<&MoveCache> {
+        self.move_cache.as_ref()
     }
 
     fn move_order_score(&self, mov: &Move) -> i32 {
@@ -66,34 +495,149 @@ impl<'a> AlphaBetaSearch<'a> {
         score
     }
 
+    /// Orders the found moves by score. Equal scores prefer the higher move order score and then the lower uci,
+    /// so the chosen move doesn't depend on the move generation order.
+    fn ranks_before(&self, (mov, score): (Move, i32), (other, other_score): (Move, i32)) -> bool {
+        score
+            .cmp(&other_score)
+            .then_with(|| {
+                self.move_order_score(&mov)
+                    .cmp(&self.move_order_score(&other))
+            })
+            .then_with(|| other.to_uci().cmp(&mov.to_uci()))
+            .is_gt()
+    }
+
+    fn make_move(&mut self, mov: Move) -> anyhow::Result<()> {
+        self.game.make_move(mov)?;
+        self.path.push(self.game.hash());
+        Ok(())
+    }
+
+    fn unmake_move(&mut self) {
+        self.game.unmake_move();
+        self.path.pop();
+    }
+
+    /// Checks if the current position is scored as a draw by repetition.
+    /// Coming back to a position of the searched line is a draw already, playing on would just repeat it.
+    /// Positions of the game before the search only count once this is their third occurrence.
+    /// Positions of other lines in the search don't count, they are not on the path anymore.
+    fn is_repetition(&self) -> bool {
+        let Some((current, earlier)) = self.path.split_last() else {
+            return false;
+        };
+        earlier.contains(current)
+            || self.history.iter().filter(|&hash| hash == current).count() >= 2
+    }
+
+    /// The score of a draw for the side to move
+    fn draw_score(&self) -> i32 {
+        if self.game.current_turn() == self.root_color {
+            -self.contempt
+        } else {
+            self.contempt
+        }
+    }
+
     fn search(&mut self, depth: u32, alpha: i32, beta: i32, update_move: bool) -> i32 {
+        let ply = (self.root_depth - depth) as usize;
+        self.stats.seldepth = self.stats.seldepth.max(ply as u32);
+        self.pv_table[ply].clear();
+        if self.reached_limit() {
+            return 0;
+        }
+        // Repeating is a draw. Playing on would just come back to the same position
+        if ply > 0 && self.is_repetition() {
+            return self.draw_score();
+        }
         if depth == 0 {
-            return self.alpha_beta_captures(8, alpha, beta);
+            if !self.quiescence {
+                return eval(self.game);
+            }
+            return self.alpha_beta_captures(self.quiescence_depth, ply as u32, alpha, beta);
         }
 
         let mut alpha = alpha;
 
         let current_color = self.game.current_turn();
-        let move_generator = MoveGenerator::new(self.game);
-        let mut legal_moves = move_generator.all_legal_moves(current_color);
-        legal_moves.sort_by_key(|mov| std::cmp::Reverse(self.move_order_score(mov)));
+        let hash = self.path.last().copied().unwrap_or_default();
+        let mut legal_moves = std::mem::take(&mut self.move_buffers[ply]);
+        legal_moves.clear();
+        {
+            #[cfg(feature = "timers")]
+            let _t = AggregatedTimer::new("move generation");
+            if let Some(move_cache) = &mut self.move_cache {
+                legal_moves
+                    .extend_from_slice(move_cache.all_legal_moves_with_hash(self.game, hash));
+            } else {
+                MoveGenerator::new(self.game).all_legal_moves_into(current_color, &mut legal_moves);
+            }
+        }
+        {
+            #[cfg(feature = "timers")]
+            let _t = AggregatedTimer::new("move ordering");
+            let hash_move = self.hash_moves.get(&hash).copied();
+            legal_moves.sort_by_key(|mov| {
+                std::cmp::Reverse((Some(*mov) == hash_move, self.move_order_score(mov)))
+            });
+        }
 
         if legal_moves.is_empty() {
-            return if self.game.is_in_check() { -1000 } else { 0 };
+            self.move_buffers[ply] = legal_moves;
+            return if self.game.is_in_check() {
+                mated_score(ply as u32)
+            } else {
+                self.draw_score()
+            };
         }
 
-        for mov in legal_moves {
-            if self.game.make_move(mov).is_err() {
+        let mut cutoff = false;
+        for (move_index, &mov) in legal_moves.iter().enumerate() {
+            let made_move = {
+                #[cfg(feature = "timers")]
+                let _t = AggregatedTimer::new("make move");
+                self.make_move(mov)
+            };
+            if made_move.is_err() {
                 println!("Failed to make move {}", mov);
                 continue;
             }
-            let eval = -self.search(depth - 1, -beta, -alpha, false);
-            self.game.unmake_move();
-            self.looked_at_positions += 1;
+            // At the root equal scores need to be exact for the tie-break, not just an upper bound
+            let child_alpha = if update_move {
+                alpha - self.root_margin
+            } else {
+                alpha
+            };
+            // The root keeps the full window, its moves need exact scores within the margin
+            let eval = if self.pvs && !update_move && move_index > 0 {
+                let eval = -self.search(depth - 1, -alpha - 1, -alpha, false);
+                if eval > alpha && eval < beta && !self.stopped {
+                    -self.search(depth - 1, -beta, -alpha, false)
+                } else {
+                    eval
+                }
+            } else {
+                -self.search(depth - 1, -beta, -child_alpha, false)
+            };
+            {
+                #[cfg(feature = "timers")]
+                let _t = AggregatedTimer::new("unmake move");
+                self.unmake_move();
+            }
+            self.stats.looked_at_positions += 1;
+            // The score of an unfinished search can't be trusted
+            if self.stopped {
+                break;
+            }
             if eval >= beta {
-                self.skipped_positions += 1;
-                return beta;
+                self.stats.count_cutoff(move_index);
+                self.hash_moves.insert(hash, mov);
+                cutoff = true;
+                break;
             }
+            // At the root the best move is decided by the tie-break as well
+            let mut new_best = eval > alpha;
             if eval > alpha {
                 alpha = eval;
             }
@@ -101,22 +645,44 @@ impl<'a> AlphaBetaSearch<'a> {
             if update_move {
                 // Find move to insert in
 
-                if let Some(pos) = self
-                    .best_moves
-                    .iter()
-                    .position(|m| m.map(|(_, score)| score < eval).unwrap_or(true))
-                {
+                if let Some(pos) = self.best_moves.iter().position(|m| {
+                    m.map(|(other, score)| self.ranks_before((mov, eval), (other, score)))
+                        .unwrap_or(true)
+                }) {
                     self.best_moves.copy_within(pos..(MAX_MOVES - 1), pos + 1);
                     self.best_moves[pos] = Some((mov, eval));
+                    new_best = pos == 0;
+                } else {
+                    new_best = false;
                 }
             }
+
+            if new_best {
+                let (current, next) = self.pv_table.split_at_mut(ply + 1);
+                current[ply].clear();
+                current[ply].push(mov);
+                current[ply].extend_from_slice(&next[0]);
+                self.hash_moves.insert(hash, mov);
+            }
         }
+        self.move_buffers[ply] = legal_moves;
 
+        if cutoff {
+            return beta;
+        }
         alpha
     }
 
-    fn alpha_beta_captures(&mut self, depth: u32, alpha: i32, beta: i32) -> i32 {
-        let eval = eval(self.game);
+    fn alpha_beta_captures(&mut self, depth: u32, ply: u32, alpha: i32, beta: i32) -> i32 {
+        self.stats.seldepth = self.stats.seldepth.max(ply);
+        if self.reached_limit() {
+            return 0;
+        }
+        let eval = {
+            #[cfg(feature = "timers")]
+            let _t = AggregatedTimer::new("eval");
+            eval(self.game)
+        };
 
         if depth == 0 {
             return eval;
@@ -143,15 +709,19 @@ impl<'a> AlphaBetaSearch<'a> {
             return eval;
         }
 
-        for mov in legal_moves {
+        for (move_index, mov) in legal_moves.into_iter().enumerate() {
             if self.game.make_move(mov).is_err() {
                 continue;
             }
 
-            let eval = -self.alpha_beta_captures(depth - 1, -beta, -alpha);
+            let eval = -self.alpha_beta_captures(depth - 1, ply + 1, -beta, -alpha);
             self.game.unmake_move();
+            self.stats.looked_at_positions += 1;
+            if self.stopped {
+                break;
+            }
             if eval >= beta {
-                self.skipped_positions += 1;
+                self.stats.count_cutoff(move_index);
                 return beta;
             }
             if eval > alpha {
@@ -162,10 +732,426 @@ impl<'a> AlphaBetaSearch<'a> {
     }
 }
 
-fn eval(game: &Game) -> i32 {
+pub(crate) fn eval(game: &Game) -> i32 {
     let current_color = game.current_turn();
     let mut score = 0;
     score += game.bitboards().material(current_color)
         - game.bitboards().material(current_color.opposite());
+    score += game.bitboards().minor_piece_score(current_color)
+        - game.bitboards().minor_piece_score(current_color.opposite());
+    score += game.bitboards().rook_file_score(current_color)
+        - game.bitboards().rook_file_score(current_color.opposite());
     score
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fen::Fen;
+
+    #[test]
+    fn bishop_pair_scores_higher() {
+        // White has the bishop pair against a bishop and a knight
+        let bishop_pair =
+            Fen::parse_game("4k3/pppppppp/8/8/8/8/PPPPPPPP/2B1KB2 w - - 0 1").unwrap();
+        let bishop_knight =
+            Fen::parse_game("4k3/pppppppp/8/8/8/8/PPPPPPPP/2B1KN2 w - - 0 1").unwrap();
+        assert!(eval(&bishop_pair) > eval(&bishop_knight));
+
+        // Two bishops on the same colored squares are not a pair
+        let same_color =
+            Fen::parse_game("4k3/pppppppp/8/8/8/8/PPPPPPPP/2B1K1B1 w - - 0 1").unwrap();
+        assert!(eval(&bishop_pair) > eval(&same_color));
+    }
+
+    #[test]
+    fn lone_knight_in_open_position() {
+        let open_knight = Fen::parse_game("4k3/pp6/8/8/8/8/PP6/4KN2 w - - 0 1").unwrap();
+        let open_bishop = Fen::parse_game("4k3/pp6/8/8/8/8/PP6/4KB2 w - - 0 1").unwrap();
+        // The knight is worth more material, but the penalty outweighs it in open positions
+        assert!(eval(&open_bishop) > eval(&open_knight));
+    }
+
+    #[test]
+    fn search_without_quiescence() {
+        let mut game =
+            Fen::parse_game("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        let legal_moves = MoveGenerator::new(&game).all_legal_moves(game.current_turn());
+
+        let mut search = AlphaBetaSearch::new(&mut game);
+        search.run(2);
+        let with_quiescence = search.stats().looked_at_positions;
+
+        let mut search = AlphaBetaSearch::new(&mut game).with_quiescence(false);
+        let best_moves = search.run(2);
+        let without_quiescence = search.stats().looked_at_positions;
+
+        assert_ne!(with_quiescence, without_quiescence);
+        let (best_move, _) = best_moves[0].expect("No move found");
+        assert!(legal_moves.contains(&best_move));
+    }
+
+    #[test]
+    fn equal_scores_are_tie_broken() {
+        let fen = "4k3/pppppppp/8/8/8/8/PPPPPPPP/4K3 w - - 0 1";
+        let run = || {
+            let mut game = Fen::parse_game(fen).unwrap();
+            AlphaBetaSearch::new(&mut game)
+                .with_quiescence(false)
+                .run(2)
+        };
+        let best_moves = run();
+        assert_eq!(best_moves, run());
+
+        // Many moves keep the material equal, the lowest uci of them comes first
+        let (first, first_score) = best_moves[0].unwrap();
+        let (second, second_score) = best_moves[1].unwrap();
+        assert_eq!(first_score, second_score);
+        assert!(first.to_uci() < second.to_uci());
+    }
+
+    #[test]
+    fn opening_randomness_stays_in_margin() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut game = Game::default();
+        let best_moves = AlphaBetaSearch::new(&mut game)
+            .with_root_margin(OPENING_MARGIN + 1)
+            .run(2);
+        let (best, best_score) = best_moves[0].unwrap();
+
+        // Without randomness always the best move
+        let bot = BotBasic::default();
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(bot.pick_move(&game, &best_moves, &mut rng), Some(best));
+
+        let bot = BotBasic::default().with_opening_randomness(2);
+        let mut rng = StdRng::seed_from_u64(1);
+        let picked = (0..20)
+            .map(|_| bot.pick_move(&game, &best_moves, &mut rng).unwrap())
+            .collect::<Vec<_>>();
+        assert!(picked.iter().any(|mov| *mov != best));
+        for mov in picked {
+            let (_, score) = best_moves
+                .iter()
+                .flatten()
+                .find(|(m, _)| *m == mov)
+                .unwrap();
+            assert!(best_score - score <= OPENING_MARGIN);
+        }
+    }
+
+    #[test]
+    fn bot_makes_legal_move() {
+        let game = Game::default();
+        let mov = BotBasic::default().make_move(&game).unwrap();
+        assert!(game.is_legal(&mov));
+    }
+
+    #[test]
+    fn mate_distance() {
+        assert_eq!(score_to_mate_distance(0), None);
+        assert_eq!(score_to_mate_distance(900), None);
+        assert_eq!(score_to_mate_distance(-mated_score(1)), Some(1));
+        assert_eq!(score_to_mate_distance(-mated_score(3)), Some(2));
+        assert_eq!(score_to_mate_distance(mated_score(2)), Some(-1));
+    }
+
+    #[test]
+    fn mate_in_two_info() {
+        // Ra7 Kg8 Rb8# (or the same with the rooks swapped)
+        let mut game = Fen::parse_game("7k/8/8/8/8/8/R7/1R5K w - - 0 1").unwrap();
+        let mut search = AlphaBetaSearch::new(&mut game);
+        let (best_move, score) = search.run(4)[0].unwrap();
+        assert_eq!(best_move.to_uci(), "a2a7");
+        assert_eq!(score_to_mate_distance(score), Some(2));
+
+        let info = uci_info(4, score, search.principal_variation());
+        assert_eq!(info, "info depth 4 score mate 2 pv a2a7 h8g8 b1b8");
+        assert_eq!(uci_info(2, 35, &[]), "info depth 2 score cp 35 pv ");
+    }
+
+    #[test]
+    fn repetition_only_on_the_same_line() {
+        let mut game = Game::default();
+        let mut search = AlphaBetaSearch::new(&mut game);
+        search.run(1);
+        let play = |search: &mut AlphaBetaSearch, moves: &[&str]| {
+            for mov in moves {
+                let mov = search.game.resolve_move(&mov.parse().unwrap()).unwrap();
+                search.make_move(mov).unwrap();
+            }
+        };
+
+        // Both lines reach the same position, but the first one is taken back
+        play(&mut search, &["g1f3", "b8c6", "b1c3"]);
+        assert!(!search.is_repetition());
+        (0..3).for_each(|_| search.unmake_move());
+        play(&mut search, &["b1c3", "b8c6", "g1f3"]);
+        assert!(!search.is_repetition());
+        (0..3).for_each(|_| search.unmake_move());
+
+        // Going back and forth on one line
+        play(&mut search, &["g1f3", "g8f6", "f3g1"]);
+        assert!(!search.is_repetition());
+        play(&mut search, &["f6g8"]);
+        assert!(search.is_repetition());
+    }
+
+    fn play_moves(game: &mut Game, moves: &[&str]) {
+        for mov in moves {
+            let mov = game.resolve_move(&mov.parse().unwrap()).unwrap();
+            game.make_move(mov).unwrap();
+        }
+    }
+
+    #[test]
+    fn repetition_with_game_history() {
+        // The start position was played once before, reaching it again is only the second time
+        let mut game = Game::default();
+        play_moves(&mut game, &["g1f3", "g8f6", "f3g1"]);
+        let mut search = AlphaBetaSearch::new(&mut game);
+        search.run(1);
+        let mov = search.game.resolve_move(&"f6g8".parse().unwrap()).unwrap();
+        search.make_move(mov).unwrap();
+        assert!(!search.is_repetition());
+
+        // Played twice before, now it's the third time
+        let mut game = Game::default();
+        play_moves(&mut game, &["g1f3", "g8f6", "f3g1", "f6g8"]);
+        play_moves(&mut game, &["g1f3", "g8f6", "f3g1"]);
+        let mut search = AlphaBetaSearch::new(&mut game);
+        search.run(1);
+        let mov = search.game.resolve_move(&"f6g8".parse().unwrap()).unwrap();
+        search.make_move(mov).unwrap();
+        assert!(search.is_repetition());
+    }
+
+    #[test]
+    fn repetition_before_the_root() {
+        // White is a pawn up, the knights go back and forth
+        let fen = "1n2k3/8/8/8/8/8/P7/4K1N1 w - - 0 1";
+        let shuffle = ["g1f3", "b8c6", "f3g1", "c6b8"];
+
+        // Black repeating the start position for the second time is no draw yet
+        let mut game = Fen::parse_game(fen).unwrap();
+        play_moves(&mut game, &shuffle[..3]);
+        let (best_move, score) = AlphaBetaSearch::new(&mut game).run(2)[0].unwrap();
+        assert!(score < 0, "{best_move} {score}");
+
+        // The third time it is, so black goes for it
+        let mut game = Fen::parse_game(fen).unwrap();
+        play_moves(&mut game, &shuffle);
+        play_moves(&mut game, &shuffle[..3]);
+        let (best_move, score) = AlphaBetaSearch::new(&mut game).run(2)[0].unwrap();
+        assert_eq!((best_move.to_uci().as_str(), score), ("c6b8", 0));
+
+        // White is winning and avoids the third Ng1
+        let mut game = Fen::parse_game(fen).unwrap();
+        play_moves(&mut game, &shuffle);
+        play_moves(&mut game, &shuffle[..2]);
+        let (best_move, score) = AlphaBetaSearch::new(&mut game).run(2)[0].unwrap();
+        assert_ne!(best_move.to_uci(), "f3g1");
+        assert!(score > 0);
+    }
+
+    #[test]
+    fn rook_on_open_file() {
+        // Rook on the open a file
+        let open = Fen::parse_game("4k3/1p6/8/8/8/8/1P6/R3K3 w - - 0 1").unwrap();
+        // Rook on the b file, only black pawn in front
+        let semi_open = Fen::parse_game("4k3/1p6/8/8/8/8/P7/1R2K3 w - - 0 1").unwrap();
+        // Rook behind its own pawn
+        let closed = Fen::parse_game("4k3/1p6/8/8/8/8/1P6/1R2K3 w - - 0 1").unwrap();
+
+        assert_eq!(eval(&open) - eval(&closed), 25);
+        assert_eq!(eval(&semi_open) - eval(&closed), 10);
+    }
+
+    #[test]
+    fn cutoff_stats() {
+        // Knight fork on king and queen
+        let mut game = Fen::parse_game("q3k3/8/8/1N6/8/8/8/4K3 w - - 0 1").unwrap();
+        let mut search = AlphaBetaSearch::new(&mut game);
+        search.run(3);
+        let stats = search.stats();
+
+        assert!(stats.cutoffs > 0);
+        assert!(stats.first_move_cutoffs <= stats.cutoffs);
+        assert!((0.0..=1.0).contains(&stats.first_move_cutoff_rate()));
+        assert_eq!(stats.depth, 3);
+        assert!(stats.effective_branching_factor() > 1.0);
+
+        assert_eq!(SearchStats::default().first_move_cutoff_rate(), 0.0);
+    }
+
+    #[test]
+    fn iterative_deepening_reuses_move_ordering() {
+        // Knight fork on king and queen
+        let fen = "q3k3/8/8/1N6/8/8/8/4K3 w - - 0 1";
+        let mut game = Fen::parse_game(fen).unwrap();
+        let mut search = AlphaBetaSearch::new(&mut game);
+        let iterative_moves = search.run_iterative(4);
+        let iterative_nodes = search.stats().looked_at_positions;
+
+        let mut game = Fen::parse_game(fen).unwrap();
+        let mut search = AlphaBetaSearch::new(&mut game);
+        let single_moves = search.run(4);
+        let single_nodes = search.stats().looked_at_positions;
+
+        assert!(iterative_nodes < 4 * single_nodes);
+        assert_eq!(iterative_moves[0], single_moves[0]);
+    }
+
+    #[test]
+    fn quiescence_depth_limit() {
+        // Both sides can keep capturing on d5
+        let fen = "3r3k/3r4/3q4/3p4/8/3R4/3R4/3QK3 w - - 0 1";
+        let mut game = Fen::parse_game(fen).unwrap();
+        let mut search = AlphaBetaSearch::new(&mut game);
+        search.run(1);
+        assert!(search.stats().seldepth > 3);
+
+        let mut game = Fen::parse_game(fen).unwrap();
+        let mut search = AlphaBetaSearch::new(&mut game).with_quiescence_depth(2);
+        search.run(1);
+        assert_eq!(search.stats().seldepth, 3);
+    }
+
+    #[test]
+    fn evaluate_position_from_white() {
+        // White is a queen up
+        for fen in [
+            "4k3/8/8/8/8/8/8/3QK3 w - - 0 1",
+            "4k3/8/8/8/8/8/8/3QK3 b - - 0 1",
+        ] {
+            let mut game = Fen::parse_game(fen).unwrap();
+            assert!(evaluate_position(&mut game, 2) > 500, "{fen}");
+        }
+        let mut game = Fen::parse_game("3qk3/8/8/8/8/8/8/4K3 w - - 0 1").unwrap();
+        assert!(evaluate_position(&mut game, 2) < -500);
+    }
+
+    #[test]
+    fn contempt_avoids_repetition() {
+        // Black is a pawn down and can repeat the position with Nb8
+        let mut game = Fen::parse_game("1n2k3/8/8/8/8/8/P7/4K1N1 w - - 0 1").unwrap();
+        play_moves(&mut game, &["g1f3", "b8c6", "f3g1", "c6b8"]);
+        play_moves(&mut game, &["g1f3", "b8c6", "f3g1"]);
+
+        let mut search = AlphaBetaSearch::new(&mut game);
+        let (best_move, score) = search.run(2)[0].unwrap();
+        assert_eq!(best_move.to_uci(), "c6b8");
+        assert_eq!(score, 0);
+
+        let mut search = AlphaBetaSearch::new(&mut game).with_contempt(200);
+        let (best_move, score) = search.run(2)[0].unwrap();
+        assert_ne!(best_move.to_uci(), "c6b8");
+        assert!(score > -200);
+    }
+
+    #[test]
+    fn move_cache_serves_transpositions() {
+        // Knight fork, the iterations and move orders reach the same positions again
+        let fen = "q3k3/8/8/1N6/8/8/8/4K3 w - - 0 1";
+        let mut game = Fen::parse_game(fen).unwrap();
+        let mut search = AlphaBetaSearch::new(&mut game);
+        let uncached = search.run_iterative(4);
+
+        let mut game = Fen::parse_game(fen).unwrap();
+        let mut search = AlphaBetaSearch::new(&mut game).with_move_cache(4096);
+        assert_eq!(search.run_iterative(4), uncached);
+        let move_cache = search.move_cache().unwrap();
+        assert!(move_cache.hit_rate() > 0.25);
+    }
+
+    #[test]
+    fn node_limit_stops_early() {
+        let mut game = Game::default();
+        let mut search = AlphaBetaSearch::new(&mut game).with_node_limit(500);
+        let (best_move, _) = search.run_iterative(6)[0].unwrap();
+        assert!(search.stopped());
+        assert!(search.stats().depth < 6);
+        assert!(search.stats().looked_at_positions < 600);
+        assert!(search.game.is_legal(&best_move));
+
+        // Stopping during the first iteration still returns a searched move
+        let mut game = Game::default();
+        let mut search = AlphaBetaSearch::new(&mut game).with_node_limit(10);
+        let (best_move, _) = search.run(3)[0].unwrap();
+        assert!(search.stopped());
+        assert!(search.game.is_legal(&best_move));
+    }
+
+    #[test]
+    fn time_limit_stops_early() {
+        let mut game = Game::default();
+        let mut search = AlphaBetaSearch::new(&mut game).with_time_limit(Duration::from_millis(50));
+        let (best_move, _) = search.run_iterative(20)[0].unwrap();
+        assert!(search.stopped());
+        assert!(search.game.is_legal(&best_move));
+
+        // No time at all still searches a root move
+        let mut game = Game::default();
+        let mut search = AlphaBetaSearch::new(&mut game).with_time_limit(Duration::ZERO);
+        let (best_move, _) = search.run_iterative(4)[0].unwrap();
+        assert!(search.game.is_legal(&best_move));
+    }
+
+    #[test]
+    fn stop_flag_stops_early() {
+        let mut game = Game::default();
+        let stop = Arc::new(AtomicBool::new(true));
+        let mut search = AlphaBetaSearch::new(&mut game).with_stop_flag(stop);
+        let (best_move, _) = search.run_iterative(20)[0].unwrap();
+        assert!(search.stopped());
+        assert_eq!(search.stats().depth, 1);
+        assert!(search.game.is_legal(&best_move));
+    }
+
+    #[test]
+    fn pvs_matches_alpha_beta() {
+        for (fen, depth) in [
+            // Queen's gambit declined
+            (
+                "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP3PPP/R2QKB1R w KQ - 0 8",
+                3,
+            ),
+            // Scholar's mate
+            (
+                "r1bqkb1r/pppp1ppp/2n2n2/4p2Q/2B1P3/8/PPPP1PPP/RNB1K1NR w KQkq - 4 4",
+                4,
+            ),
+            // Back rank mate
+            ("6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - - 0 1", 5),
+        ] {
+            let mut game = Fen::parse_game(fen).unwrap();
+            let mut search = AlphaBetaSearch::new(&mut game).with_pvs(false);
+            let alpha_beta = search.run_iterative(depth)[0];
+            let alpha_beta_nodes = search.stats().looked_at_positions;
+
+            let mut game = Fen::parse_game(fen).unwrap();
+            let mut search = AlphaBetaSearch::new(&mut game);
+            let pvs = search.run_iterative(depth)[0];
+            let pvs_nodes = search.stats().looked_at_positions;
+
+            assert_eq!(pvs, alpha_beta, "{fen}");
+            assert!(pvs_nodes <= alpha_beta_nodes, "{fen}");
+        }
+    }
+
+    #[test]
+    fn solve_mate_in_one_epd() {
+        let position = "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - -";
+        assert!(solve_epd(
+            &format!("{position} bm Rd8#; id \"back rank\";"),
+            2
+        ));
+        assert!(solve_epd(&format!("{position} bm Kf1 Rd8#;"), 2));
+        assert!(!solve_epd(&format!("{position} bm Rd7;"), 2));
+        // No or an illegal best move
+        assert!(!solve_epd(&format!("{position} id \"back rank\";"), 2));
+        assert!(!solve_epd(&format!("{position} bm Rd9;"), 2));
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{
+    color::Color, game::CastleRights, piece::Piece, piece_type::PieceType, position::Position,
+};
+
+/// Random keys for zobrist hashing.
+/// The keys are generated at compile time from a fixed seed, so hashes are stable between runs.
+pub struct Zobrist;
+
+const PIECE_KEYS: [[u64; 64]; 12] = generate_piece_keys();
+const BLACK_TO_MOVE_KEY: u64 = splitmix64(0xB1AC_0000_0000_0001).1;
+const CASTLE_KEYS: [u64; 4] = generate_keys::<4>(0xCA57_1E00_0000_0000);
+const EN_PASSANT_KEYS: [u64; 8] = generate_keys::<8>(0xE9A5_5A97_0000_0000);
+
+impl Zobrist {
+    pub fn piece(piece: &Piece, position: &Position) -> u64 {
+        let color_offset = match piece.color() {
+            Color::White => 0,
+            Color::Black => 6,
+        };
+        let type_index = match piece.piece_type() {
+            PieceType::Pawn => 0,
+            PieceType::Knight => 1,
+            PieceType::Bishop => 2,
+            PieceType::Rook => 3,
+            PieceType::Queen => 4,
+            PieceType::King => 5,
+        };
+        PIECE_KEYS[color_offset + type_index][position.board_index()]
+    }
+
+    pub fn black_to_move() -> u64 {
+        BLACK_TO_MOVE_KEY
+    }
+
+    pub fn castle_rights(color: Color, rights: CastleRights) -> u64 {
+        let offset = match color {
+            Color::White => 0,
+            Color::Black => 2,
+        };
+        let mut key = 0;
+        if rights.king_side() {
+            key ^= CASTLE_KEYS[offset];
+        }
+        if rights.queen_side() {
+            key ^= CASTLE_KEYS[offset + 1];
+        }
+        key
+    }
+
+    pub fn en_passant_file(file: u8) -> u64 {
+        EN_PASSANT_KEYS[file as usize]
+    }
+}
+
+const fn splitmix64(state: u64) -> (u64, u64) {
+    let state = state.wrapping_add(0x9E3779B97F4A7C15);
+    let mut z = state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    (state, z ^ (z >> 31))
+}
+
+const fn generate_keys<const N: usize>(seed: u64) -> [u64; N] {
+    let mut keys = [0; N];
+    let mut state = seed;
+    let mut i = 0;
+    while i < N {
+        let (next_state, key) = splitmix64(state);
+        state = next_state;
+        keys[i] = key;
+        i += 1;
+    }
+    keys
+}
+
+const fn generate_piece_keys() -> [[u64; 64]; 12] {
+    let mut keys = [[0; 64]; 12];
+    let mut state = 0x5EED_0000_0000_0000;
+    let mut piece = 0;
+    while piece < 12 {
+        let mut square = 0;
+        while square < 64 {
+            let (next_state, key) = splitmix64(state);
+            state = next_state;
+            keys[piece][square] = key;
+            square += 1;
+        }
+        piece += 1;
+    }
+    keys
+}